@@ -0,0 +1,57 @@
+//! Compares the row-kernel conversion against the per-pixel reference.
+//!
+//! Run with `cargo bench --bench convert --features bench`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use wayland_client::protocol::wl_shm;
+
+#[allow(dead_code)]
+#[path = "../src/convert.rs"]
+mod convert;
+
+/// A 5120x1440 super-ultrawide window, the case that prompted the rewrite.
+const WIDTH: u32 = 5120;
+const HEIGHT: u32 = 1440;
+
+fn frame(format: wl_shm::Format) -> (Vec<u8>, u32) {
+    let stride = WIDTH * convert::bytes_per_pixel(format).unwrap() as u32;
+    // Pseudo-random bytes, so translucent formats exercise the unpremultiply path.
+    let mut state = 0x2545_f491u32;
+    let data = (0..stride * HEIGHT)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    (data, stride)
+}
+
+fn conversion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("convert_to_rgba");
+    group.throughput(Throughput::Elements(u64::from(WIDTH * HEIGHT)));
+    group.sample_size(20);
+    for format in [
+        wl_shm::Format::Xrgb8888,
+        wl_shm::Format::Argb8888,
+        wl_shm::Format::Xrgb2101010,
+        wl_shm::Format::Rgb565,
+    ] {
+        let (data, stride) = frame(format);
+        let name = format!("{format:?}");
+        group.bench_with_input(BenchmarkId::new("scalar", &name), &data, |b, data| {
+            b.iter(|| {
+                convert::convert_to_rgba_scalar(black_box(data), WIDTH, HEIGHT, stride, format, false)
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("kernels", &name), &data, |b, data| {
+            b.iter(|| convert::convert_to_rgba(black_box(data), WIDTH, HEIGHT, stride, format, false))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, conversion);
+criterion_main!(benches);
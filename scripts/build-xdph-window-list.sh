@@ -37,10 +37,10 @@ for c in clients:
     cls = c.get("class", "")
     addr = c.get("address", "0")
     try:
-        handle_lo = int(str(addr), 16) & 0xFFFFFFFF
+        mapped_id = int(str(addr), 16)
     except Exception:
-        handle_lo = 0
-    mapped_id = 0
+        mapped_id = 0
+    handle_lo = mapped_id & 0xFFFFFFFF
     parts.append(f"{handle_lo}[HC>]{cls}[HT>]{title}[HE>]{mapped_id}[HA>]")
 print("".join(parts))
 '
@@ -0,0 +1,208 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+pub const APP_DIR: &str = "hyprland-better-share-picker";
+
+/// User configuration read from `$XDG_CONFIG_HOME/hyprland-better-share-picker/config.toml`.
+///
+/// Every field has a default, so a missing or partial file is fine.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    /// Drop child toplevels (dialogs, popups) instead of stacking them under their parent tile.
+    pub hide_child_windows: bool,
+    /// What translucent thumbnails are drawn over.
+    pub thumbnail_backdrop: Backdrop,
+    /// How a window is framed in its tile. Rules can override it per window.
+    pub thumbnail_mode: ThumbnailMode,
+    /// Run an unsharp mask over thumbnails after downscaling.
+    pub sharpen: bool,
+    /// Draw a thin border around previews to separate them from the tile.
+    pub contrast_frame: bool,
+    /// How many windows may be captured at once; the rest wait their turn.
+    pub max_concurrent_captures: usize,
+    /// Target for pixel memory (shm pool plus thumbnails), in MiB. Only thumbnails of windows
+    /// scrolled out of view are dropped to meet it, and recaptured when they come back.
+    pub memory_budget_mb: usize,
+    /// Draw the cursor into thumbnails. Also `--overlay-cursor`, and toggleable in the picker.
+    pub overlay_cursor: bool,
+    /// Show a larger preview of the hovered or focused window in a side pane.
+    pub zoom_pane: bool,
+    /// Remember shared windows (class and title only) to offer them in a Recent row.
+    pub share_history: bool,
+    /// Outline the window behind the hovered or focused tile on screen.
+    pub highlight_windows: bool,
+    /// Start by picking the window on screen with the pointer instead of from the grid. Also
+    /// `--pick-on-screen`.
+    pub pick_on_screen: bool,
+    /// Icon theme for app icons. Defaults to GTK's `gtk-icon-theme-name`, then `hicolor`.
+    pub icon_theme: Option<String>,
+    /// Per-window overrides, written as `[[rule]]` tables.
+    #[serde(rename = "rule")]
+    pub rules: Vec<Rule>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            hide_child_windows: false,
+            thumbnail_backdrop: Backdrop::default(),
+            thumbnail_mode: ThumbnailMode::default(),
+            sharpen: false,
+            contrast_frame: false,
+            max_concurrent_captures: 4,
+            memory_budget_mb: 256,
+            overlay_cursor: false,
+            zoom_pane: true,
+            icon_theme: None,
+            share_history: true,
+            highlight_windows: true,
+            pick_on_screen: false,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Rules matching a window, in file order.
+    pub fn rules_for<'a>(&'a self, class: &'a str, title: &'a str) -> impl Iterator<Item = &'a Rule> {
+        self.rules.iter().filter(move |rule| rule.matches(class, title))
+    }
+
+    pub fn hides_preview(&self, class: &str, title: &str) -> bool {
+        self.rules_for(class, title).any(|rule| rule.hide_preview)
+    }
+
+    /// Thumbnail treatment for a window: the global settings, overridden by matching rules
+    /// in file order, so later rules win.
+    pub fn style_for(&self, class: &str, title: &str) -> ThumbnailStyle {
+        let base = ThumbnailStyle {
+            mode: self.thumbnail_mode,
+            sharpen: self.sharpen,
+            contrast_frame: self.contrast_frame,
+        };
+        self.rules_for(class, title).fold(base, |style, rule| ThumbnailStyle {
+            mode: rule.thumbnail_mode.unwrap_or(style.mode),
+            sharpen: rule.sharpen.unwrap_or(style.sharpen),
+            contrast_frame: rule.contrast_frame.unwrap_or(style.contrast_frame),
+        })
+    }
+
+    /// What to do to a window before sharing it; the last matching rule that sets it wins.
+    pub fn share_actions_for<'a>(&'a self, class: &'a str, title: &'a str) -> &'a [ShareAction] {
+        self.rules_for(class, title)
+            .filter_map(|rule| rule.before_share.as_deref())
+            .last()
+            .unwrap_or_default()
+    }
+}
+
+/// Resolved per-window thumbnail settings; see [`Config::style_for`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThumbnailStyle {
+    pub mode: ThumbnailMode,
+    pub sharpen: bool,
+    pub contrast_frame: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThumbnailMode {
+    /// The whole window, letterboxed into the tile.
+    #[default]
+    Fit,
+    /// Fill the tile, cropping the window's edges around its centre.
+    FillCrop,
+    /// Fill the tile with the window's top-left corner (title, tab and address bars) at a
+    /// scale where text stays readable.
+    TopCrop,
+}
+
+/// Settings applied to windows whose class and title match. Empty matchers match anything.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Rule {
+    /// Window class / app_id, compared case-insensitively.
+    pub class: String,
+    /// Case-insensitive substring of the window title.
+    pub title_contains: String,
+    /// Never capture or show this window's contents.
+    pub hide_preview: bool,
+    pub thumbnail_mode: Option<ThumbnailMode>,
+    pub sharpen: Option<bool>,
+    pub contrast_frame: Option<bool>,
+    /// Actions run through Hyprland's IPC when the window is chosen, before the portal is
+    /// told; see [`crate::share_actions`].
+    pub before_share: Option<Vec<ShareAction>>,
+}
+
+impl Rule {
+    pub fn matches(&self, class: &str, title: &str) -> bool {
+        let class_matches = self.class.is_empty() || self.class.eq_ignore_ascii_case(class);
+        let title_matches = self.title_contains.is_empty()
+            || title
+                .to_lowercase()
+                .contains(&self.title_contains.to_lowercase());
+        class_matches && title_matches
+    }
+}
+
+/// Something done to a window so it can actually be streamed. Windows on hidden or special
+/// workspaces aren't rendered, so neither is their stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShareAction {
+    /// Move the window to the workspace on the focused monitor.
+    BringHere,
+    /// Open the special (scratchpad) workspace the window is on, if it is closed.
+    ShowSpecial,
+    /// Take the window out of fullscreen.
+    Unfullscreen,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backdrop {
+    /// Draw straight onto the tile, like any other image.
+    #[default]
+    None,
+    /// A checkerboard, so transparency is obvious at a glance.
+    Checkerboard,
+    /// The theme's background colour, approximating what a viewer of the share will see.
+    Theme,
+}
+
+pub fn get() -> &'static Config {
+    static CONFIG: OnceLock<Config> = OnceLock::new();
+    CONFIG.get_or_init(load)
+}
+
+fn load() -> Config {
+    let Some(path) = config_path() else {
+        return Config::default();
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Config::default(),
+        Err(error) => {
+            eprintln!("Config error: failed to read {}: {error}", path.display());
+            return Config::default();
+        }
+    };
+    match toml::from_str(&contents) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("Config error: {}: {error}", path.display());
+            Config::default()
+        }
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join(APP_DIR).join("config.toml"))
+}
@@ -0,0 +1,676 @@
+use rayon::prelude::*;
+use std::fmt;
+use wayland_client::protocol::wl_shm;
+
+/// Largest source buffer we are willing to allocate and read, in bytes. Comfortably above a
+/// full 8K window in any supported format.
+pub const MAX_FRAME_BYTES: usize = 256 * 1024 * 1024;
+
+/// Largest width or height accepted from the compositor.
+pub const MAX_DIMENSION: u32 = 16384;
+
+/// `wl_shm` formats we can turn into RGBA8. Layouts follow the DRM fourcc definitions,
+/// which are little-endian packed values.
+const SUPPORTED_FORMATS: &[wl_shm::Format] = &[
+    wl_shm::Format::Argb8888,
+    wl_shm::Format::Xrgb8888,
+    wl_shm::Format::Abgr8888,
+    wl_shm::Format::Xbgr8888,
+    wl_shm::Format::Bgr888,
+    wl_shm::Format::Rgb565,
+    wl_shm::Format::Argb2101010,
+    wl_shm::Format::Xrgb2101010,
+    wl_shm::Format::Abgr2101010,
+    wl_shm::Format::Xbgr2101010,
+];
+
+pub fn is_supported(format: wl_shm::Format) -> bool {
+    SUPPORTED_FORMATS.contains(&format)
+}
+
+pub fn bytes_per_pixel(format: wl_shm::Format) -> Option<usize> {
+    match format {
+        wl_shm::Format::Bgr888 => Some(3),
+        wl_shm::Format::Rgb565 => Some(2),
+        format if is_supported(format) => Some(4),
+        _ => None,
+    }
+}
+
+/// Buffer metadata announced by the compositor, checked before any memory is touched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameLayout {
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+    pub format: wl_shm::Format,
+    bytes_per_pixel: usize,
+}
+
+impl FrameLayout {
+    pub fn new(
+        width: u32,
+        height: u32,
+        stride: u32,
+        format: wl_shm::Format,
+    ) -> Result<Self, FrameError> {
+        let bytes_per_pixel = bytes_per_pixel(format).ok_or(FrameError::UnsupportedFormat(format))?;
+        if width == 0 || height == 0 {
+            return Err(FrameError::Empty);
+        }
+        if width > MAX_DIMENSION || height > MAX_DIMENSION {
+            return Err(FrameError::TooLarge { width, height });
+        }
+
+        let row_bytes = width as u64 * bytes_per_pixel as u64;
+        if u64::from(stride) < row_bytes {
+            return Err(FrameError::StrideTooSmall { stride, row_bytes });
+        }
+        let buffer_bytes = u64::from(stride) * u64::from(height);
+        let output_bytes = u64::from(width) * u64::from(height) * 4;
+        if buffer_bytes.max(output_bytes) > MAX_FRAME_BYTES as u64 {
+            return Err(FrameError::TooLarge { width, height });
+        }
+
+        Ok(Self {
+            width,
+            height,
+            stride,
+            format,
+            bytes_per_pixel,
+        })
+    }
+
+    /// Size of the shm buffer the compositor copies into, `stride * height`.
+    pub fn buffer_len(&self) -> usize {
+        self.stride as usize * self.height as usize
+    }
+
+    fn row_bytes(&self) -> usize {
+        self.width as usize * self.bytes_per_pixel
+    }
+
+    /// Source bytes for output row `y`. Callers validate `data` against [`Self::required_len`].
+    fn source_row<'a>(&self, data: &'a [u8], y: usize, y_invert: bool) -> &'a [u8] {
+        let src_y = if y_invert { self.height as usize - 1 - y } else { y };
+        let start = src_y * self.stride as usize;
+        &data[start..start + self.row_bytes()]
+    }
+
+    /// Bytes actually read: the last row does not need its stride padding.
+    fn required_len(&self) -> usize {
+        self.stride as usize * (self.height as usize - 1) + self.row_bytes()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    UnsupportedFormat(wl_shm::Format),
+    Empty,
+    TooLarge { width: u32, height: u32 },
+    StrideTooSmall { stride: u32, row_bytes: u64 },
+    BufferTooSmall { len: usize, required: usize },
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::UnsupportedFormat(format) => write!(f, "unsupported shm format {format:?}"),
+            FrameError::Empty => write!(f, "empty frame"),
+            FrameError::TooLarge { width, height } => {
+                write!(f, "frame {width}x{height} exceeds the size limit")
+            }
+            FrameError::StrideTooSmall { stride, row_bytes } => {
+                write!(f, "stride {stride} is shorter than a {row_bytes}-byte row")
+            }
+            FrameError::BufferTooSmall { len, required } => {
+                write!(f, "buffer holds {len} bytes but the frame needs {required}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+/// Rows converted per rayon task; smaller frames aren't worth splitting further.
+const MIN_ROWS_PER_TASK: usize = 16;
+
+/// Converts a `wl_shm` frame to tightly packed straight-alpha RGBA8, flipping it when the
+/// compositor set `y_invert`. Rows are converted in parallel with per-format kernels.
+pub fn convert_to_rgba(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: wl_shm::Format,
+    y_invert: bool,
+) -> Result<Vec<u8>, FrameError> {
+    let layout = checked_layout(data, width, height, stride, format)?;
+    let kernel = row_kernel(format);
+    let dst_row_bytes = width as usize * 4;
+    let mut out = vec![0u8; dst_row_bytes * height as usize];
+    out.par_chunks_exact_mut(dst_row_bytes)
+        .enumerate()
+        .with_min_len(MIN_ROWS_PER_TASK)
+        .for_each(|(y, dst_row)| kernel(layout.source_row(data, y, y_invert), dst_row));
+    Ok(out)
+}
+
+/// Pixel-at-a-time reference for [`convert_to_rgba`], kept for equivalence tests and the
+/// benchmark.
+#[cfg(any(test, feature = "bench"))]
+#[cfg_attr(feature = "bench", allow(dead_code))]
+pub fn convert_to_rgba_scalar(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: wl_shm::Format,
+    y_invert: bool,
+) -> Result<Vec<u8>, FrameError> {
+    let layout = checked_layout(data, width, height, stride, format)?;
+    let bpp = layout.bytes_per_pixel;
+    let dst_row_bytes = width as usize * 4;
+    let mut out = vec![0u8; dst_row_bytes * height as usize];
+    for (y, dst_row) in out.chunks_exact_mut(dst_row_bytes).enumerate() {
+        let src_row = layout.source_row(data, y, y_invert);
+        for (dst, src) in dst_row.chunks_exact_mut(4).zip(src_row.chunks_exact(bpp)) {
+            dst.copy_from_slice(&decode_pixel(format, src));
+        }
+    }
+    Ok(out)
+}
+
+fn checked_layout(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: wl_shm::Format,
+) -> Result<FrameLayout, FrameError> {
+    let layout = FrameLayout::new(width, height, stride, format)?;
+    if data.len() < layout.required_len() {
+        return Err(FrameError::BufferTooSmall {
+            len: data.len(),
+            required: layout.required_len(),
+        });
+    }
+    Ok(layout)
+}
+
+/// Converts one row: `src` holds exactly `width` source pixels, `dst` exactly `width` RGBA
+/// pixels.
+type RowKernel = fn(src: &[u8], dst: &mut [u8]);
+
+fn row_kernel(format: wl_shm::Format) -> RowKernel {
+    match format {
+        wl_shm::Format::Argb8888 => row_8888::<true, false>,
+        wl_shm::Format::Xrgb8888 => row_8888::<true, true>,
+        wl_shm::Format::Abgr8888 => row_8888::<false, false>,
+        wl_shm::Format::Xbgr8888 => row_8888::<false, true>,
+        wl_shm::Format::Argb2101010 => row_2101010::<true, false>,
+        wl_shm::Format::Xrgb2101010 => row_2101010::<true, true>,
+        wl_shm::Format::Abgr2101010 => row_2101010::<false, false>,
+        wl_shm::Format::Xbgr2101010 => row_2101010::<false, true>,
+        wl_shm::Format::Bgr888 => row_bgr888,
+        wl_shm::Format::Rgb565 => row_rgb565,
+        // FrameLayout rejects everything else before a kernel is picked.
+        _ => |_, dst| dst.fill(0),
+    }
+}
+
+// The kernels below stick to fixed-size chunks and whole-word bit operations, with no
+// per-pixel branches, so LLVM turns them into SIMD shuffles on any target.
+
+/// 8-bit channels. `SWAP_RB` moves red from bits 16..24 to 0..8 (the `*RGB` formats);
+/// `OPAQUE` overwrites the padding byte of the `X*` formats.
+fn row_8888<const SWAP_RB: bool, const OPAQUE: bool>(src: &[u8], dst: &mut [u8]) {
+    for (s, d) in src.chunks_exact(4).zip(dst.chunks_exact_mut(4)) {
+        let mut px = u32::from_le_bytes([s[0], s[1], s[2], s[3]]);
+        if SWAP_RB {
+            px = (px & 0xff00_ff00) | ((px >> 16) & 0xff) | ((px & 0xff) << 16);
+        }
+        if OPAQUE {
+            px |= 0xff00_0000;
+        }
+        d.copy_from_slice(&px.to_le_bytes());
+    }
+    if !OPAQUE {
+        unpremultiply_row(dst);
+    }
+}
+
+/// 10-bit channels with 2-bit alpha; `SWAP_RB` and `OPAQUE` as for [`row_8888`].
+fn row_2101010<const SWAP_RB: bool, const OPAQUE: bool>(src: &[u8], dst: &mut [u8]) {
+    for (s, d) in src.chunks_exact(4).zip(dst.chunks_exact_mut(4)) {
+        let px = u32::from_le_bytes([s[0], s[1], s[2], s[3]]);
+        let (high, mid, low) = (
+            reduce_10((px >> 20) & 0x3ff),
+            reduce_10((px >> 10) & 0x3ff),
+            reduce_10(px & 0x3ff),
+        );
+        let (r, b) = if SWAP_RB { (high, low) } else { (low, high) };
+        let a = if OPAQUE { 0xff } else { ((px >> 30) & 0x3) as u8 * 0x55 };
+        d.copy_from_slice(&[r, mid, b, a]);
+    }
+    if !OPAQUE {
+        unpremultiply_row(dst);
+    }
+}
+
+fn row_bgr888(src: &[u8], dst: &mut [u8]) {
+    for (s, d) in src.chunks_exact(3).zip(dst.chunks_exact_mut(4)) {
+        d.copy_from_slice(&[s[0], s[1], s[2], 0xff]);
+    }
+}
+
+fn row_rgb565(src: &[u8], dst: &mut [u8]) {
+    for (s, d) in src.chunks_exact(2).zip(dst.chunks_exact_mut(4)) {
+        let px = u16::from_le_bytes([s[0], s[1]]);
+        d.copy_from_slice(&[
+            expand_5((px >> 11) & 0x1f),
+            expand_6((px >> 5) & 0x3f),
+            expand_5(px & 0x1f),
+            0xff,
+        ]);
+    }
+}
+
+/// Most windows are fully opaque, so a branch-free scan of the alpha bytes lets their rows
+/// skip the lookup entirely.
+fn unpremultiply_row(row: &mut [u8]) {
+    let all_opaque = row.chunks_exact(4).fold(0xff, |alpha, px| alpha & px[3]) == 0xff;
+    if all_opaque {
+        return;
+    }
+    for px in row.chunks_exact_mut(4) {
+        let table = &UNPREMULTIPLY[usize::from(px[3])];
+        px[0] = table[usize::from(px[0])];
+        px[1] = table[usize::from(px[1])];
+        px[2] = table[usize::from(px[2])];
+    }
+}
+
+/// [`unpremultiply`] for every `[alpha][channel]` pair, so translucent rows cost three table
+/// reads per pixel instead of three divisions.
+static UNPREMULTIPLY: [[u8; 256]; 256] = unpremultiply_table();
+
+const fn unpremultiply_table() -> [[u8; 256]; 256] {
+    let mut table = [[0u8; 256]; 256];
+    let mut alpha = 1;
+    while alpha < 256 {
+        let mut channel = 0;
+        while channel < 256 {
+            let straight = (channel * 255 + alpha / 2) / alpha;
+            table[alpha][channel] = if straight > 255 { 255 } else { straight as u8 };
+            channel += 1;
+        }
+        alpha += 1;
+    }
+    table
+}
+
+/// Pixels inspected by [`is_blank`]; enough to catch a real image without walking every pixel.
+const BLANK_SAMPLES: usize = 1024;
+/// Alpha or luma at or below this counts as "nothing there".
+const BLANK_THRESHOLD: u8 = 8;
+
+/// Cheaply detects frames that are all black or fully transparent, which some clients hand out
+/// before they've rendered anything. Samples an evenly spaced subset of RGBA pixels and calls the
+/// frame blank when every sample is dark or invisible.
+pub fn is_blank(rgba: &[u8]) -> bool {
+    let pixels = rgba.len() / 4;
+    if pixels == 0 {
+        return true;
+    }
+    let step = (pixels / BLANK_SAMPLES).max(1);
+    rgba.chunks_exact(4).step_by(step).all(|px| {
+        let [r, g, b, a] = [px[0], px[1], px[2], px[3]];
+        // Rec. 601 luma in integer math.
+        let luma = (u32::from(r) * 299 + u32::from(g) * 587 + u32::from(b) * 114) / 1000;
+        a <= BLANK_THRESHOLD || luma <= u32::from(BLANK_THRESHOLD)
+    })
+}
+
+/// Decodes one source pixel into straight-alpha `[r, g, b, a]`.
+#[cfg(any(test, feature = "bench"))]
+#[cfg_attr(feature = "bench", allow(dead_code))]
+fn decode_pixel(format: wl_shm::Format, src: &[u8]) -> [u8; 4] {
+    match format {
+        wl_shm::Format::Bgr888 => [src[0], src[1], src[2], 0xff],
+        wl_shm::Format::Rgb565 => {
+            let px = u16::from_le_bytes([src[0], src[1]]);
+            [
+                expand_5((px >> 11) & 0x1f),
+                expand_6((px >> 5) & 0x3f),
+                expand_5(px & 0x1f),
+                0xff,
+            ]
+        }
+        _ => {
+            let px = u32::from_le_bytes([src[0], src[1], src[2], src[3]]);
+            unpremultiply(decode_packed_32(format, px))
+        }
+    }
+}
+
+/// Compositors hand out premultiplied alpha, while `image::Handle::from_rgba` wants straight
+/// alpha. Opaque formats pass through untouched since their alpha is always 0xff.
+pub fn unpremultiply([r, g, b, a]: [u8; 4]) -> [u8; 4] {
+    match a {
+        0xff => [r, g, b, a],
+        0 => [0, 0, 0, 0],
+        _ => {
+            let alpha = u16::from(a);
+            let channel = |c: u8| ((u16::from(c) * 255 + alpha / 2) / alpha).min(255) as u8;
+            [channel(r), channel(g), channel(b), a]
+        }
+    }
+}
+
+/// Inverse of [`unpremultiply`], for filters that must not bleed colour out of transparent
+/// pixels.
+pub fn premultiply([r, g, b, a]: [u8; 4]) -> [u8; 4] {
+    match a {
+        0xff => [r, g, b, a],
+        0 => [0, 0, 0, 0],
+        _ => {
+            let alpha = u16::from(a);
+            let channel = |c: u8| ((u16::from(c) * alpha + 127) / 255) as u8;
+            [channel(r), channel(g), channel(b), a]
+        }
+    }
+}
+
+#[cfg(any(test, feature = "bench"))]
+#[cfg_attr(feature = "bench", allow(dead_code))]
+fn decode_packed_32(format: wl_shm::Format, px: u32) -> [u8; 4] {
+    let byte = |shift: u32| ((px >> shift) & 0xff) as u8;
+    let ten = |shift: u32| reduce_10((px >> shift) & 0x3ff);
+    let alpha_2 = ((px >> 30) & 0x3) as u8 * 0x55;
+    match format {
+        wl_shm::Format::Argb8888 => [byte(16), byte(8), byte(0), byte(24)],
+        wl_shm::Format::Xrgb8888 => [byte(16), byte(8), byte(0), 0xff],
+        wl_shm::Format::Abgr8888 => [byte(0), byte(8), byte(16), byte(24)],
+        wl_shm::Format::Xbgr8888 => [byte(0), byte(8), byte(16), 0xff],
+        wl_shm::Format::Argb2101010 => [ten(20), ten(10), ten(0), alpha_2],
+        wl_shm::Format::Xrgb2101010 => [ten(20), ten(10), ten(0), 0xff],
+        wl_shm::Format::Abgr2101010 => [ten(0), ten(10), ten(20), alpha_2],
+        wl_shm::Format::Xbgr2101010 => [ten(0), ten(10), ten(20), 0xff],
+        _ => [0, 0, 0, 0xff],
+    }
+}
+
+/// Replicates the high bits into the low ones so 0x1f maps to 0xff, not 0xf8.
+fn expand_5(value: u16) -> u8 {
+    ((value << 3) | (value >> 2)) as u8
+}
+
+fn expand_6(value: u16) -> u8 {
+    ((value << 2) | (value >> 4)) as u8
+}
+
+/// Rounds a 10-bit channel to the nearest 8-bit value instead of truncating.
+fn reduce_10(value: u32) -> u8 {
+    ((value * 255 + 511) / 1023) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert_one(format: wl_shm::Format, pixel: &[u8]) -> [u8; 4] {
+        let rgba = convert_to_rgba(pixel, 1, 1, pixel.len() as u32, format, false).unwrap();
+        [rgba[0], rgba[1], rgba[2], rgba[3]]
+    }
+
+    #[test]
+    fn argb8888() {
+        let px = 0xff_11_22_33u32.to_le_bytes();
+        assert_eq!(convert_one(wl_shm::Format::Argb8888, &px), [0x11, 0x22, 0x33, 0xff]);
+    }
+
+    #[test]
+    fn argb8888_unpremultiplies() {
+        // 50% alpha over premultiplied channels doubles them back up.
+        let px = 0x80_11_22_33u32.to_le_bytes();
+        assert_eq!(convert_one(wl_shm::Format::Argb8888, &px), [0x22, 0x44, 0x66, 0x80]);
+    }
+
+    #[test]
+    fn fully_transparent_pixels_clear_colour() {
+        let px = 0x00_11_22_33u32.to_le_bytes();
+        assert_eq!(convert_one(wl_shm::Format::Argb8888, &px), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn unpremultiply_table_matches_formula() {
+        for a in 0..=255u8 {
+            for c in 0..=255u8 {
+                let mut row = [c, c, c, a];
+                unpremultiply_row(&mut row);
+                assert_eq!(row, unpremultiply([c, c, c, a]), "alpha {a}, channel {c}");
+            }
+        }
+    }
+
+    #[test]
+    fn unpremultiply_clamps_out_of_range_channels() {
+        // Invalid premultiplied data (colour > alpha) must saturate, not wrap.
+        let px = 0x10_ff_80_08u32.to_le_bytes();
+        assert_eq!(convert_one(wl_shm::Format::Argb8888, &px), [0xff, 0xff, 0x80, 0x10]);
+    }
+
+    #[test]
+    fn xrgb8888_ignores_padding() {
+        let px = 0x12_11_22_33u32.to_le_bytes();
+        assert_eq!(convert_one(wl_shm::Format::Xrgb8888, &px), [0x11, 0x22, 0x33, 0xff]);
+    }
+
+    #[test]
+    fn abgr8888() {
+        let px = 0x80_33_22_11u32.to_le_bytes();
+        assert_eq!(convert_one(wl_shm::Format::Abgr8888, &px), [0x22, 0x44, 0x66, 0x80]);
+    }
+
+    #[test]
+    fn xbgr8888_ignores_padding() {
+        let px = 0x00_33_22_11u32.to_le_bytes();
+        assert_eq!(convert_one(wl_shm::Format::Xbgr8888, &px), [0x11, 0x22, 0x33, 0xff]);
+    }
+
+    #[test]
+    fn bgr888() {
+        assert_eq!(
+            convert_one(wl_shm::Format::Bgr888, &[0x11, 0x22, 0x33]),
+            [0x11, 0x22, 0x33, 0xff]
+        );
+    }
+
+    #[test]
+    fn rgb565() {
+        let white = 0xffffu16.to_le_bytes();
+        assert_eq!(convert_one(wl_shm::Format::Rgb565, &white), [0xff, 0xff, 0xff, 0xff]);
+
+        // r = 0b10000, g = 0b100000, b = 0b00001
+        let px = ((0x10u16 << 11) | (0x20 << 5) | 0x01).to_le_bytes();
+        assert_eq!(convert_one(wl_shm::Format::Rgb565, &px), [0x84, 0x82, 0x08, 0xff]);
+    }
+
+    #[test]
+    fn argb2101010() {
+        let px = ((0x2u32 << 30) | (0x2aa << 20) | (0x155 << 10) | 0x001).to_le_bytes();
+        assert_eq!(convert_one(wl_shm::Format::Argb2101010, &px), [0xff, 0x80, 0x00, 0xaa]);
+    }
+
+    #[test]
+    fn xrgb2101010() {
+        let px = ((0x100u32 << 20) | (0x3ff << 10)).to_le_bytes();
+        assert_eq!(convert_one(wl_shm::Format::Xrgb2101010, &px), [0x40, 0xff, 0x00, 0xff]);
+    }
+
+    #[test]
+    fn abgr2101010() {
+        let px = ((0x3u32 << 30) | (0x001 << 20) | (0x200 << 10) | 0x3ff).to_le_bytes();
+        assert_eq!(convert_one(wl_shm::Format::Abgr2101010, &px), [0xff, 0x80, 0x00, 0xff]);
+    }
+
+    #[test]
+    fn xbgr2101010() {
+        let px = ((0x1u32 << 30) | (0x3ff << 20) | 0x100).to_le_bytes();
+        assert_eq!(convert_one(wl_shm::Format::Xbgr2101010, &px), [0x40, 0x00, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn honours_stride_and_y_invert() {
+        // Two rows of one Xrgb8888 pixel each, padded to an 8-byte stride.
+        let mut data = Vec::new();
+        data.extend_from_slice(&0x00_ff_00_00u32.to_le_bytes());
+        data.extend_from_slice(&[0xee; 4]);
+        data.extend_from_slice(&0x00_00_00_ffu32.to_le_bytes());
+        data.extend_from_slice(&[0xee; 4]);
+
+        let rgba = convert_to_rgba(&data, 1, 2, 8, wl_shm::Format::Xrgb8888, true).unwrap();
+        assert_eq!(rgba, [0x00, 0x00, 0xff, 0xff, 0xff, 0x00, 0x00, 0xff]);
+    }
+
+    #[test]
+    fn last_row_needs_no_stride_padding() {
+        let data = [0u8; 8 + 4];
+        assert!(convert_to_rgba(&data, 1, 2, 8, wl_shm::Format::Xrgb8888, false).is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_metadata() {
+        let data = [0u8; 64];
+        let format = wl_shm::Format::Argb8888;
+        assert_eq!(convert_to_rgba(&data, 0, 4, 16, format, false), Err(FrameError::Empty));
+        assert_eq!(
+            convert_to_rgba(&data, 4, 4, 8, format, false),
+            Err(FrameError::StrideTooSmall { stride: 8, row_bytes: 16 })
+        );
+        assert_eq!(
+            convert_to_rgba(&data, 4, 5, 16, format, false),
+            Err(FrameError::BufferTooSmall { len: 64, required: 80 })
+        );
+        assert_eq!(
+            convert_to_rgba(&data, u32::MAX, u32::MAX, u32::MAX, format, false),
+            Err(FrameError::TooLarge { width: u32::MAX, height: u32::MAX })
+        );
+        assert_eq!(
+            convert_to_rgba(&data, 4, 4, 16, wl_shm::Format::Nv12, false),
+            Err(FrameError::UnsupportedFormat(wl_shm::Format::Nv12))
+        );
+    }
+
+    #[test]
+    fn rejects_frames_over_the_byte_budget() {
+        // Dimensions pass individually, but the stride pushes the buffer over the cap.
+        let layout = FrameLayout::new(4096, 16384, u32::MAX / 8, wl_shm::Format::Argb8888);
+        assert_eq!(layout, Err(FrameError::TooLarge { width: 4096, height: 16384 }));
+    }
+
+    #[test]
+    fn black_and_transparent_frames_are_blank() {
+        let black: Vec<u8> = [0, 0, 0, 0xff].repeat(64 * 64);
+        let transparent: Vec<u8> = [0xff, 0xff, 0xff, 0].repeat(64 * 64);
+        assert!(is_blank(&black));
+        assert!(is_blank(&transparent));
+        assert!(is_blank(&[]));
+    }
+
+    #[test]
+    fn frames_with_content_are_not_blank() {
+        let mut frame: Vec<u8> = [0, 0, 0, 0xff].repeat(64 * 64);
+        // A single grey row in an otherwise black frame is enough.
+        for px in frame[32 * 64 * 4..33 * 64 * 4].chunks_exact_mut(4) {
+            px.copy_from_slice(&[0x80, 0x80, 0x80, 0xff]);
+        }
+        assert!(!is_blank(&frame));
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn any_format() -> impl Strategy<Value = wl_shm::Format> {
+            prop_oneof![
+                Just(wl_shm::Format::Argb8888),
+                Just(wl_shm::Format::Xrgb8888),
+                Just(wl_shm::Format::Abgr8888),
+                Just(wl_shm::Format::Xbgr8888),
+                Just(wl_shm::Format::Bgr888),
+                Just(wl_shm::Format::Rgb565),
+                Just(wl_shm::Format::Argb2101010),
+                Just(wl_shm::Format::Xrgb2101010),
+                Just(wl_shm::Format::Abgr2101010),
+                Just(wl_shm::Format::Xbgr2101010),
+                Just(wl_shm::Format::Nv12),
+                Just(wl_shm::Format::C8),
+            ]
+        }
+
+        proptest! {
+            /// Arbitrary metadata against an arbitrary buffer must never panic, and a success
+            /// must always produce exactly `width * height` RGBA pixels.
+            #[test]
+            fn never_panics_on_arbitrary_metadata(
+                data in proptest::collection::vec(any::<u8>(), 0..4096),
+                width in prop_oneof![0u32..64, any::<u32>()],
+                height in prop_oneof![0u32..64, any::<u32>()],
+                stride in prop_oneof![0u32..512, any::<u32>()],
+                format in any_format(),
+                y_invert in any::<bool>(),
+            ) {
+                if let Ok(rgba) = convert_to_rgba(&data, width, height, stride, format, y_invert) {
+                    prop_assert_eq!(rgba.len(), width as usize * height as usize * 4);
+                }
+            }
+
+            /// Well-formed frames with padded strides always convert.
+            #[test]
+            fn converts_every_valid_frame(
+                width in 1u32..48,
+                height in 1u32..48,
+                padding in 0u32..16,
+                format in any_format().prop_filter("convertible", |f| is_supported(*f)),
+                y_invert in any::<bool>(),
+                seed in any::<u8>(),
+            ) {
+                let bpp = bytes_per_pixel(format).unwrap() as u32;
+                let stride = width * bpp + padding;
+                let data: Vec<u8> = (0..stride * height).map(|i| (i as u8).wrapping_mul(seed)).collect();
+                let rgba = convert_to_rgba(&data, width, height, stride, format, y_invert);
+                prop_assert!(rgba.is_ok());
+            }
+
+            /// The row kernels must match the per-pixel reference bit for bit.
+            #[test]
+            fn kernels_match_scalar_reference(
+                width in 1u32..80,
+                height in 1u32..40,
+                padding in 0u32..16,
+                format in any_format().prop_filter("convertible", |f| is_supported(*f)),
+                y_invert in any::<bool>(),
+                data in proptest::collection::vec(any::<u8>(), 80 * 4 * 40 + 16 * 40),
+            ) {
+                let bpp = bytes_per_pixel(format).unwrap() as u32;
+                let stride = width * bpp + padding;
+                let data = &data[..(stride * height) as usize];
+                prop_assert_eq!(
+                    convert_to_rgba(data, width, height, stride, format, y_invert),
+                    convert_to_rgba_scalar(data, width, height, stride, format, y_invert)
+                );
+            }
+
+            /// Straight alpha output never has colour in fully transparent pixels.
+            #[test]
+            fn transparent_pixels_carry_no_colour(pixel in any::<[u8; 4]>()) {
+                let rgba = convert_to_rgba(&pixel, 1, 1, 4, wl_shm::Format::Argb8888, false).unwrap();
+                if rgba[3] == 0 {
+                    prop_assert_eq!(&rgba[..3], &[0, 0, 0]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use crate::config;
+use iced::widget::{image, svg};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Icons are picked closest to this size; tiles draw them at 16–48 logical pixels.
+const ICON_SIZE: u32 = 48;
+
+/// What a desktop entry says about a window's application.
+#[derive(Debug, Clone)]
+pub struct AppInfo {
+    /// The entry's `Name`, e.g. "WezTerm" for `org.wezfurlong.wezterm`.
+    pub name: String,
+    pub icon: Option<Icon>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Icon {
+    Raster(image::Handle),
+    Vector(svg::Handle),
+}
+
+/// Looks up the application behind each window class. Classes without a desktop entry are
+/// left out. Reads the filesystem, so run it off the UI thread.
+pub fn resolve<'a>(classes: impl IntoIterator<Item = &'a str>) -> HashMap<String, AppInfo> {
+    let mut icons: HashMap<String, Option<Icon>> = HashMap::new();
+    let mut apps = HashMap::new();
+    for class in classes {
+        if class.is_empty() || apps.contains_key(class) {
+            continue;
+        }
+        let Some(entry) = find_entry(entries(), class) else { continue };
+        let icon = entry.icon.as_deref().and_then(|name| {
+            icons
+                .entry(name.to_string())
+                .or_insert_with(|| find_icon(name))
+                .clone()
+        });
+        apps.insert(
+            class.to_string(),
+            AppInfo {
+                name: entry.name.clone(),
+                icon,
+            },
+        );
+    }
+    apps
+}
+
+/// The parts of a `.desktop` file the picker uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    /// Desktop file id, lowercased and without `.desktop`, e.g. `org.wezfurlong.wezterm`.
+    id: String,
+    name: String,
+    icon: Option<String>,
+    /// `StartupWMClass`, lowercased.
+    wm_class: Option<String>,
+}
+
+/// Every application entry on the system, read once. Earlier data directories shadow later
+/// ones, as in the XDG spec.
+fn entries() -> &'static [Entry] {
+    static ENTRIES: OnceLock<Vec<Entry>> = OnceLock::new();
+    ENTRIES.get_or_init(|| {
+        let mut seen = HashSet::new();
+        let mut entries = Vec::new();
+        for dir in data_dirs() {
+            let Ok(files) = std::fs::read_dir(dir.join("applications")) else { continue };
+            for file in files.flatten() {
+                let path = file.path();
+                let Some(id) = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.strip_suffix(".desktop"))
+                    .map(str::to_lowercase)
+                else {
+                    continue;
+                };
+                if !seen.insert(id.clone()) {
+                    continue;
+                }
+                if let Some(entry) = std::fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|contents| parse_entry(id, &contents))
+                {
+                    entries.push(entry);
+                }
+            }
+        }
+        entries
+    })
+}
+
+/// Parses the `[Desktop Entry]` group. Hidden entries and non-applications yield nothing.
+fn parse_entry(id: String, contents: &str) -> Option<Entry> {
+    let mut in_group = false;
+    let (mut name, mut icon, mut wm_class) = (None, None, None);
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_group = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_group {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let value = value.trim();
+        match key.trim() {
+            "Name" => name = Some(value.to_string()),
+            "Icon" if !value.is_empty() => icon = Some(value.to_string()),
+            "StartupWMClass" => wm_class = Some(value.to_lowercase()),
+            "Type" if value != "Application" => return None,
+            "Hidden" if value == "true" => return None,
+            _ => {}
+        }
+    }
+    Some(Entry {
+        id,
+        name: name?,
+        icon,
+        wm_class,
+    })
+}
+
+/// Matches a class against, in order: `StartupWMClass`, the desktop file id, then the last
+/// reverse-DNS component of either side (`org.mozilla.firefox` ↔ `firefox`), all
+/// case-insensitively.
+fn find_entry<'a>(entries: &'a [Entry], class: &str) -> Option<&'a Entry> {
+    let class = class.to_lowercase();
+    let short = last_component(&class);
+    let dashed = class.replace(' ', "-");
+    entries
+        .iter()
+        .find(|entry| entry.wm_class.as_deref() == Some(class.as_str()))
+        .or_else(|| entries.iter().find(|entry| entry.id == class || entry.id == dashed))
+        .or_else(|| entries.iter().find(|entry| last_component(&entry.id) == short))
+}
+
+fn last_component(id: &str) -> &str {
+    id.rsplit('.').next().unwrap_or(id)
+}
+
+/// Resolves an `Icon` value through the icon theme, its parents and `hicolor`, then the
+/// legacy pixmaps directory. Absolute paths are used as they are.
+fn find_icon(name: &str) -> Option<Icon> {
+    let path = if Path::new(name).is_absolute() {
+        Some(PathBuf::from(name)).filter(|path| path.is_file())
+    } else {
+        themed_icon(name).or_else(|| unthemed_icon(name))
+    }?;
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("svg") => Some(Icon::Vector(svg::Handle::from_path(path))),
+        Some("png") => Some(Icon::Raster(image::Handle::from_path(path))),
+        _ => None,
+    }
+}
+
+fn themed_icon(name: &str) -> Option<PathBuf> {
+    let mut queue = vec![current_theme()];
+    let mut visited = HashSet::new();
+    while let Some(theme) = queue.pop() {
+        if !visited.insert(theme.clone()) {
+            continue;
+        }
+        let mut best: Option<(u32, PathBuf)> = None;
+        let mut parents = Vec::new();
+        for base in icon_dirs() {
+            let root = base.join(&theme);
+            let Ok(index) = std::fs::read_to_string(root.join("index.theme")) else { continue };
+            let index = parse_theme_index(&index);
+            parents.extend(index.inherits.iter().cloned());
+            for dir in &index.directories {
+                for extension in ["svg", "png"] {
+                    let path = root.join(&dir.path).join(format!("{name}.{extension}"));
+                    let distance = dir.distance(ICON_SIZE);
+                    if best.as_ref().is_none_or(|(best, _)| distance < *best) && path.is_file() {
+                        best = Some((distance, path));
+                    }
+                }
+            }
+        }
+        if let Some((_, path)) = best {
+            return Some(path);
+        }
+        // Depth-first through `Inherits`, in order, with hicolor as the last resort.
+        if parents.is_empty() && theme != "hicolor" {
+            parents.push("hicolor".to_string());
+        }
+        queue.extend(parents.into_iter().rev());
+    }
+    None
+}
+
+fn unthemed_icon(name: &str) -> Option<PathBuf> {
+    let mut dirs = icon_dirs();
+    dirs.push(PathBuf::from("/usr/share/pixmaps"));
+    dirs.iter()
+        .flat_map(|dir| ["svg", "png"].map(|extension| dir.join(format!("{name}.{extension}"))))
+        .find(|path| path.is_file())
+}
+
+struct ThemeIndex {
+    inherits: Vec<String>,
+    directories: Vec<IconDir>,
+}
+
+struct IconDir {
+    path: String,
+    size: u32,
+    min_size: u32,
+    max_size: u32,
+    scalable: bool,
+}
+
+impl IconDir {
+    /// How far this directory's icons are from `size`; zero means a perfect fit.
+    fn distance(&self, size: u32) -> u32 {
+        if self.scalable && (self.min_size..=self.max_size).contains(&size) {
+            0
+        } else {
+            self.size.abs_diff(size)
+        }
+    }
+}
+
+/// Reads `Inherits` and the per-directory `Size`, `MinSize`, `MaxSize` and `Type` keys of an
+/// `index.theme`.
+fn parse_theme_index(contents: &str) -> ThemeIndex {
+    let mut inherits = Vec::new();
+    let mut listed = Vec::new();
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut section = String::new();
+    for line in contents.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            section = name.to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let (key, value) = (key.trim(), value.trim());
+        if section == "Icon Theme" {
+            let list = || value.split(',').map(str::trim).filter(|item| !item.is_empty());
+            match key {
+                "Inherits" => inherits = list().map(str::to_string).collect(),
+                "Directories" | "ScaledDirectories" => listed.extend(list().map(str::to_string)),
+                _ => {}
+            }
+        } else {
+            sections
+                .entry(section.clone())
+                .or_default()
+                .insert(key.to_string(), value.to_string());
+        }
+    }
+
+    let directories = listed
+        .into_iter()
+        .filter_map(|path| {
+            let keys = sections.get(&path)?;
+            let number = |key: &str| keys.get(key).and_then(|value| value.parse::<u32>().ok());
+            let size = number("Size")?;
+            Some(IconDir {
+                size,
+                min_size: number("MinSize").unwrap_or(size),
+                max_size: number("MaxSize").unwrap_or(size),
+                scalable: keys.get("Type").is_some_and(|kind| kind == "Scalable"),
+                path,
+            })
+        })
+        .collect();
+    ThemeIndex {
+        inherits,
+        directories,
+    }
+}
+
+/// `icon-theme` from the config, else GTK's `gtk-icon-theme-name`, else `hicolor`.
+fn current_theme() -> String {
+    if let Some(theme) = &config::get().icon_theme {
+        return theme.clone();
+    }
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    config_home
+        .into_iter()
+        .flat_map(|dir| ["gtk-4.0", "gtk-3.0"].map(|gtk| dir.join(gtk).join("settings.ini")))
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .find_map(|contents| {
+            contents.lines().find_map(|line| {
+                let (key, value) = line.split_once('=')?;
+                (key.trim() == "gtk-icon-theme-name").then(|| value.trim().to_string())
+            })
+        })
+        .unwrap_or_else(|| "hicolor".to_string())
+}
+
+/// `$XDG_DATA_HOME` followed by `$XDG_DATA_DIRS`, with the spec's defaults.
+fn data_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home.map(|home| home.join(".local").join("share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    data_home
+        .into_iter()
+        .chain(data_dirs.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from))
+        .collect()
+}
+
+/// `~/.icons`, then `icons` under each data directory.
+fn icon_dirs() -> Vec<PathBuf> {
+    let legacy = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".icons"));
+    legacy
+        .into_iter()
+        .chain(data_dirs().into_iter().map(|dir| dir.join("icons")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, wm_class: Option<&str>) -> Entry {
+        Entry {
+            id: id.to_string(),
+            name: id.to_string(),
+            icon: None,
+            wm_class: wm_class.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parses_only_the_desktop_entry_group() {
+        let contents = "[Desktop Entry]\nType=Application\nName=WezTerm\nName[de]=WezTerm DE\n\
+                        Icon=org.wezfurlong.wezterm\nStartupWMClass=WezTerm\n\n\
+                        [Desktop Action new]\nName=New Window\n";
+        let parsed = parse_entry("org.wezfurlong.wezterm".into(), contents).unwrap();
+        assert_eq!(parsed.name, "WezTerm");
+        assert_eq!(parsed.icon.as_deref(), Some("org.wezfurlong.wezterm"));
+        assert_eq!(parsed.wm_class.as_deref(), Some("wezterm"));
+        assert!(parse_entry("link".into(), "[Desktop Entry]\nType=Link\nName=x\n").is_none());
+        assert!(parse_entry("gone".into(), "[Desktop Entry]\nName=x\nHidden=true\n").is_none());
+    }
+
+    #[test]
+    fn matches_wm_class_then_id_then_reverse_dns() {
+        let entries = [
+            entry("org.mozilla.firefox", None),
+            entry("code", Some("code-url-handler")),
+            entry("org.wezfurlong.wezterm", None),
+            entry("steam", Some("steam")),
+        ];
+        let id = |class| find_entry(&entries, class).map(|entry| entry.id.as_str());
+        assert_eq!(id("firefox"), Some("org.mozilla.firefox"));
+        assert_eq!(id("Code-URL-Handler"), Some("code"));
+        assert_eq!(id("org.wezfurlong.wezterm"), Some("org.wezfurlong.wezterm"));
+        assert_eq!(id("com.example.wezterm"), Some("org.wezfurlong.wezterm"));
+        assert_eq!(id("Steam"), Some("steam"));
+        assert_eq!(id("unknown"), None);
+    }
+
+    #[test]
+    fn picks_the_closest_icon_directory() {
+        let index = parse_theme_index(
+            "[Icon Theme]\nInherits=Adwaita,hicolor\nDirectories=16x16/apps,scalable/apps\n\n\
+             [16x16/apps]\nSize=16\n\n\
+             [scalable/apps]\nSize=128\nMinSize=8\nMaxSize=512\nType=Scalable\n",
+        );
+        assert_eq!(index.inherits, ["Adwaita", "hicolor"]);
+        let distances: Vec<u32> = index.directories.iter().map(|dir| dir.distance(48)).collect();
+        assert_eq!(distances, [32, 0]);
+    }
+}
@@ -0,0 +1,182 @@
+use iced::futures::task::AtomicWaker;
+use iced::futures::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+
+/// Creates a bounded queue from blocking threads to an async consumer.
+///
+/// A full queue blocks senders until the consumer catches up, except that
+/// [`Sender::send_coalescing`] can replace a queued item it supersedes without waiting.
+pub fn bounded<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            items: VecDeque::with_capacity(capacity),
+            senders: 1,
+            receiver_alive: true,
+        }),
+        space: Condvar::new(),
+        waker: AtomicWaker::new(),
+        capacity: capacity.max(1),
+    });
+    (Sender(shared.clone()), Receiver(shared))
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    /// Signalled whenever the consumer takes an item or goes away.
+    space: Condvar,
+    waker: AtomicWaker,
+    capacity: usize,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        // Nothing panics while holding the lock, but don't turn a poisoned queue into a crash.
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+struct State<T> {
+    items: VecDeque<T>,
+    senders: usize,
+    receiver_alive: bool,
+}
+
+pub struct Sender<T>(Arc<Shared<T>>);
+
+impl<T> Sender<T> {
+    /// Queues an item, blocking while the queue is full. Returns `false` if the receiver is
+    /// gone and the item was dropped.
+    pub fn send(&self, item: T) -> bool {
+        self.push(item, |_| false)
+    }
+
+    /// Like [`Sender::send`], but first replaces the oldest queued item for which
+    /// `supersedes` is true. The replacement keeps the old item's place in line.
+    pub fn send_coalescing(&self, item: T, supersedes: impl Fn(&T) -> bool) -> bool {
+        self.push(item, supersedes)
+    }
+
+    fn push(&self, item: T, supersedes: impl Fn(&T) -> bool) -> bool {
+        let mut state = self.0.lock();
+        if let Some(queued) = state.items.iter_mut().find(|queued| supersedes(queued)) {
+            *queued = item;
+            return true;
+        }
+        while state.receiver_alive && state.items.len() >= self.0.capacity {
+            state = self
+                .0
+                .space
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        if !state.receiver_alive {
+            return false;
+        }
+        state.items.push_back(item);
+        drop(state);
+        self.0.waker.wake();
+        true
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.0.lock().senders += 1;
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.0.lock().senders -= 1;
+        self.0.waker.wake();
+    }
+}
+
+/// Yields queued items in order and ends once every sender is dropped and the queue drained.
+pub struct Receiver<T>(Arc<Shared<T>>);
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        // Register first so a send racing with this poll can't be missed.
+        self.0.waker.register(cx.waker());
+        let mut state = self.0.lock();
+        match state.items.pop_front() {
+            Some(item) => {
+                drop(state);
+                self.0.space.notify_one();
+                Poll::Ready(Some(item))
+            }
+            None if state.senders == 0 => Poll::Ready(None),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.receiver_alive = false;
+        state.items.clear();
+        drop(state);
+        self.0.space.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::futures::executor::block_on;
+    use iced::futures::StreamExt;
+
+    #[test]
+    fn delivers_in_order_and_ends_with_the_senders() {
+        let (sender, receiver) = bounded(4);
+        assert!(sender.send(1));
+        assert!(sender.send(2));
+        drop(sender);
+        assert_eq!(block_on(receiver.collect::<Vec<_>>()), [1, 2]);
+    }
+
+    #[test]
+    fn coalescing_replaces_in_place_even_when_full() {
+        let (sender, receiver) = bounded(2);
+        assert!(sender.send((1, "old")));
+        assert!(sender.send((2, "other")));
+        assert!(sender.send_coalescing((1, "new"), |queued| queued.0 == 1));
+        drop(sender);
+        assert_eq!(
+            block_on(receiver.collect::<Vec<_>>()),
+            [(1, "new"), (2, "other")]
+        );
+    }
+
+    #[test]
+    fn full_queue_blocks_until_the_consumer_catches_up() {
+        let (sender, mut receiver) = bounded(1);
+        let producer = std::thread::spawn(move || (0..10).all(|i| sender.send(i)));
+        let received: Vec<_> = block_on(async {
+            let mut received = Vec::new();
+            while let Some(item) = receiver.next().await {
+                received.push(item);
+            }
+            received
+        });
+        assert!(producer.join().unwrap());
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn dropped_receiver_unblocks_senders() {
+        let (sender, receiver) = bounded(1);
+        assert!(sender.send(1));
+        let producer = std::thread::spawn(move || sender.send(2));
+        drop(receiver);
+        assert!(!producer.join().unwrap());
+    }
+}
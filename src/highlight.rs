@@ -0,0 +1,222 @@
+use crate::hyprland::{self, Client, Monitor};
+use crate::overlay::{self, Content, HIGHLIGHT_BORDER, Overlay, Painter};
+use crate::wayland::debug_log;
+use iced::Rectangle;
+use smithay_client_toolkit::compositor::Region;
+use smithay_client_toolkit::output::OutputState;
+use smithay_client_toolkit::reexports::calloop::EventLoop;
+use smithay_client_toolkit::reexports::calloop::channel;
+use smithay_client_toolkit::reexports::calloop_wayland_source::WaylandSource;
+use smithay_client_toolkit::registry::{ProvidesRegistryState, RegistryState};
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::{Anchor, LayerSurface};
+use smithay_client_toolkit::{delegate_registry, registry_handlers};
+use std::fmt;
+
+const OUTLINE_WIDTH: i32 = 4;
+
+/// Sending half of the command channel into the outline thread.
+#[derive(Clone)]
+pub struct HighlightHandle(channel::Sender<Option<u64>>);
+
+impl HighlightHandle {
+    /// Outlines the window with this mapped id, or nothing. Best effort: without
+    /// layer-shell or Hyprland's IPC there is simply no outline.
+    pub fn show(&self, mapped_id: Option<u64>) {
+        let _ = self.0.send(mapped_id);
+    }
+}
+
+impl fmt::Debug for HighlightHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HighlightHandle")
+    }
+}
+
+/// Starts the thread that outlines the window behind the hovered or focused tile, so two
+/// tiles that look alike can be told apart. It connects right away but draws nothing until
+/// asked to.
+///
+/// The outline is a layer-shell surface on a Wayland connection of its own rather than a
+/// border colour changed through IPC: the compositor destroys it along with the connection,
+/// so nothing is left to revert when the picker exits, crashes or is killed.
+pub fn spawn() -> HighlightHandle {
+    let (sender, receiver) = channel::channel();
+    std::thread::spawn(move || {
+        if let Err(error) = run(receiver) {
+            debug_log(&format!("highlight: {error}"));
+        }
+    });
+    HighlightHandle(sender)
+}
+
+/// Where to draw the outline: the monitor showing the window, and the window's area relative
+/// to that monitor's top-left corner. `None` if the window isn't on screen.
+pub fn placement<'a>(
+    clients: &[Client],
+    monitors: &'a [Monitor],
+    mapped_id: u64,
+) -> Option<(&'a Monitor, Rectangle)> {
+    let client = clients
+        .iter()
+        .find(|client| client.id() == Some(mapped_id))?;
+    let monitor = monitors
+        .iter()
+        .find(|monitor| monitor.shows(client.workspace.id))?;
+    let (window, origin) = (client.rect(), monitor.rect());
+    let rect = Rectangle {
+        x: window.x - origin.x,
+        y: window.y - origin.y,
+        ..window
+    };
+    (rect.width >= 1.0 && rect.height >= 1.0).then_some((monitor, rect))
+}
+
+fn run(commands: channel::Channel<Option<u64>>) -> Result<(), Box<dyn std::error::Error>> {
+    let (conn, queue, mut overlay) = overlay::connect(|_, _| Highlight {
+        outline: None,
+        pending: None,
+    })?;
+    let mut event_loop = EventLoop::<Overlay<Highlight>>::try_new()?;
+    event_loop
+        .handle()
+        .insert_source(commands, |event, _, overlay| {
+            if let channel::Event::Msg(target) = event {
+                overlay.content.pending = Some(target);
+            }
+        })
+        .map_err(|error| error.error)?;
+    WaylandSource::new(conn, queue)
+        .insert(event_loop.handle())
+        .map_err(|error| error.error)?;
+    // Only the latest request matters, so a burst of hover changes costs one IPC query.
+    event_loop.run(None, &mut overlay, apply)?;
+    Ok(())
+}
+
+struct Highlight {
+    /// The outline on screen; dropping it destroys the surface.
+    outline: Option<LayerSurface>,
+    /// The window to outline next, if that changed since the last loop iteration.
+    pending: Option<Option<u64>>,
+}
+
+fn apply(overlay: &mut Overlay<Highlight>) {
+    let Some(target) = overlay.content.pending.take() else {
+        return;
+    };
+    overlay.content.outline = None;
+    let Some(mapped_id) = target else { return };
+    let (clients, monitors) = match hyprland::clients()
+        .and_then(|clients| hyprland::monitors().map(|monitors| (clients, monitors)))
+    {
+        Ok(reply) => reply,
+        Err(error) => {
+            debug_log(&format!("highlight: can't locate window: {error}"));
+            return;
+        }
+    };
+    let Some((monitor, rect)) = placement(&clients, &monitors, mapped_id) else {
+        return;
+    };
+    let Some(output) = overlay.output_state.outputs().find(|output| {
+        overlay
+            .output_state
+            .info(output)
+            .and_then(|info| info.name)
+            .is_some_and(|name| name == monitor.name)
+    }) else {
+        return;
+    };
+
+    let layer = overlay.create_layer(&output);
+    // The outline must not take clicks or hover away from the window under it.
+    if let Ok(region) = Region::new(&overlay.compositor) {
+        layer
+            .wl_surface()
+            .set_input_region(Some(region.wl_region()));
+    }
+    layer.set_anchor(Anchor::TOP | Anchor::LEFT);
+    layer.set_margin(rect.y as i32, 0, 0, rect.x as i32);
+    layer.set_size(rect.width as u32, rect.height as u32);
+    layer.commit();
+    overlay.content.outline = Some(layer);
+}
+
+impl Content for Highlight {
+    /// Draws the outline just inside the window's edges, transparent in the middle.
+    fn configure(
+        &mut self,
+        painter: &mut Painter,
+        layer: &LayerSurface,
+        (width, height): (u32, u32),
+    ) {
+        if self.outline.as_ref() != Some(layer) {
+            return;
+        }
+        let window = Rectangle {
+            x: 0,
+            y: 0,
+            width: width as i32,
+            height: height as i32,
+        };
+        let drawn = painter.draw(layer, (width, height), |canvas| {
+            canvas.frame(window, OUTLINE_WIDTH, HIGHLIGHT_BORDER, 0);
+        });
+        if let Err(error) = drawn {
+            debug_log(&format!("highlight: {error}"));
+        }
+    }
+
+    fn closed(&mut self, layer: &LayerSurface) {
+        if self.outline.as_ref() == Some(layer) {
+            self.outline = None;
+        }
+    }
+}
+
+impl ProvidesRegistryState for Overlay<Highlight> {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+    registry_handlers![OutputState];
+}
+
+delegate_registry!(Overlay<Highlight>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hyprland::fixtures::{client, monitor};
+
+    #[test]
+    fn places_the_outline_on_the_monitor_showing_the_window() {
+        let monitors = [monitor(0, "DP-1", 0, 1), monitor(1, "DP-2", 1920, 2)];
+        let clients = [
+            Client {
+                at: [2020, 50],
+                size: [800, 600],
+                ..client("0x55d0c6b1e2a0", 2)
+            },
+            Client {
+                size: [800, 600],
+                ..client("0x55d0c6b1f310", 3)
+            },
+        ];
+        let (monitor, rect) = placement(&clients, &monitors, 0x55d0_c6b1_e2a0).unwrap();
+        assert_eq!(monitor.name, "DP-2");
+        assert_eq!(
+            rect,
+            Rectangle {
+                x: 100.0,
+                y: 50.0,
+                width: 800.0,
+                height: 600.0
+            }
+        );
+        // Hidden workspaces and unknown windows get no outline; windows are found by their
+        // whole address, not its low bits.
+        assert!(placement(&clients, &monitors, 0x55d0_c6b1_f310).is_none());
+        assert!(placement(&clients, &monitors, 0xc6b1_e2a0).is_none());
+    }
+}
@@ -0,0 +1,200 @@
+use crate::state;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HISTORY_FILE: &str = "history.toml";
+
+/// Each share's weight halves after this long, so last week's habit fades behind today's.
+const HALF_LIFE_SECS: f64 = 7.0 * 24.0 * 60.0 * 60.0;
+/// Share times kept per window; older ones barely count anyway.
+const MAX_SHARE_TIMES: usize = 10;
+/// Windows remembered; the lowest-scoring ones are forgotten first.
+const MAX_RECORDS: usize = 200;
+
+/// What was shared and when, in `$XDG_STATE_HOME/hyprland-better-share-picker/history.toml`.
+/// Only classes and normalized titles are stored, never pixels.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct History {
+    /// Classes pinned to the Recent row, lowercased.
+    pinned: Vec<String>,
+    #[serde(rename = "share")]
+    shares: Vec<ShareRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ShareRecord {
+    /// Lowercased class.
+    class: String,
+    /// See [`normalize_title`].
+    title: String,
+    /// Unix seconds of the latest shares, oldest first.
+    times: Vec<u64>,
+}
+
+impl History {
+    pub fn record(&mut self, class: &str, title: &str, now: u64) {
+        let (class, title) = (class.to_lowercase(), normalize_title(title));
+        match self
+            .shares
+            .iter_mut()
+            .find(|record| record.class == class && record.title == title)
+        {
+            Some(record) => {
+                record.times.push(now);
+                let excess = record.times.len().saturating_sub(MAX_SHARE_TIMES);
+                record.times.drain(..excess);
+            }
+            None => self.shares.push(ShareRecord {
+                class,
+                title,
+                times: vec![now],
+            }),
+        }
+        if self.shares.len() > MAX_RECORDS {
+            self.shares
+                .sort_by(|a, b| b.score(now).total_cmp(&a.score(now)));
+            self.shares.truncate(MAX_RECORDS);
+        }
+    }
+
+    /// Frecency of a window: every past share counts, recent ones most. Zero if never shared.
+    pub fn score(&self, class: &str, title: &str, now: u64) -> f64 {
+        let (class, title) = (class.to_lowercase(), normalize_title(title));
+        self.shares
+            .iter()
+            .find(|record| record.class == class && record.title == title)
+            .map_or(0.0, |record| record.score(now))
+    }
+
+    pub fn is_pinned(&self, class: &str) -> bool {
+        self.pinned.contains(&class.to_lowercase())
+    }
+
+    pub fn toggle_pin(&mut self, class: &str) {
+        let class = class.to_lowercase();
+        match self.pinned.iter().position(|pinned| *pinned == class) {
+            Some(index) => {
+                self.pinned.remove(index);
+            }
+            None => self.pinned.push(class),
+        }
+    }
+
+    pub fn has_shares(&self) -> bool {
+        !self.shares.is_empty()
+    }
+
+    /// Forgets every share; pins are kept, since the user set them explicitly.
+    pub fn clear_shares(&mut self) {
+        self.shares.clear();
+    }
+}
+
+impl ShareRecord {
+    fn score(&self, now: u64) -> f64 {
+        self.times
+            .iter()
+            .map(|&time| 0.5f64.powf(now.saturating_sub(time) as f64 / HALF_LIFE_SECS))
+            .sum()
+    }
+}
+
+/// Strips the parts of a title that change while the window stays the same: unread counters
+/// like `(3)`, unsaved-changes markers, case and runs of whitespace.
+fn normalize_title(title: &str) -> String {
+    let mut title = title.trim();
+    loop {
+        let stripped = title
+            .trim_start_matches(['*', '●', '•'])
+            .trim_end_matches(['*', '●', '•'])
+            .trim();
+        let stripped = strip_counter(stripped);
+        if stripped == title {
+            break;
+        }
+        title = stripped;
+    }
+    title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Drops a leading `(12)` or `[12]`.
+fn strip_counter(title: &str) -> &str {
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        if let Some(rest) = title.strip_prefix(open)
+            && let Some((count, rest)) = rest.split_once(close)
+            && !count.is_empty()
+            && count.chars().all(|c| c.is_ascii_digit() || c == '+')
+        {
+            return rest.trim_start();
+        }
+    }
+    title
+}
+
+pub fn load() -> History {
+    state::read(HISTORY_FILE)
+}
+
+pub fn save(history: &History) {
+    state::write(HISTORY_FILE, history);
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    #[test]
+    fn normalizes_volatile_title_parts() {
+        assert_eq!(normalize_title("(3) Slack | general"), "slack | general");
+        assert_eq!(normalize_title("[12+] Inbox"), "inbox");
+        assert_eq!(normalize_title("● main.rs  —  Code"), "main.rs — code");
+        assert_eq!(normalize_title("*notes.txt - gedit"), "notes.txt - gedit");
+        assert_eq!(normalize_title("(draft) Letter"), "(draft) letter");
+    }
+
+    #[test]
+    fn recent_shares_outrank_old_habits() {
+        let now = 100 * DAY;
+        let mut history = History::default();
+        for day in 0..5 {
+            history.record("Firefox", "Docs", now - 60 * DAY + day * DAY);
+        }
+        history.record("kitty", "shell", now - DAY);
+        assert!(history.score("kitty", "shell", now) > history.score("firefox", "docs", now));
+        assert_eq!(history.score("kitty", "(2) shell", now), history.score("kitty", "shell", now));
+        assert_eq!(history.score("kitty", "other", now), 0.0);
+    }
+
+    #[test]
+    fn keeps_a_bounded_history() {
+        let mut history = History::default();
+        for i in 0..(MAX_SHARE_TIMES as u64 + 5) {
+            history.record("kitty", "shell", i);
+        }
+        assert_eq!(history.shares[0].times.len(), MAX_SHARE_TIMES);
+        for i in 0..(MAX_RECORDS + 1) {
+            history.record("app", &format!("window {i}"), 1);
+        }
+        assert_eq!(history.shares.len(), MAX_RECORDS);
+        // The most shared window survives the trim.
+        assert!(history.score("kitty", "shell", 20) > 0.0);
+
+        history.toggle_pin("Kitty");
+        history.clear_shares();
+        assert!(!history.has_shares() && history.is_pinned("kitty"));
+    }
+}
@@ -0,0 +1,353 @@
+use crate::portal::PortalWindow;
+use iced::Rectangle;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Hyprland answers instantly; anything slower means it is wedged, and the picker must not be.
+const IPC_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A window as Hyprland's `clients` request describes it. Only the fields the picker uses.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Client {
+    /// Hex address such as `0x55d0c6b1e2a0`, Hyprland's identity for the window.
+    pub address: String,
+    pub workspace: WorkspaceRef,
+    #[serde(default)]
+    pub floating: bool,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub fullscreen: Fullscreen,
+    /// Position in Hyprland's focus history; 0 is the most recently focused window.
+    #[serde(rename = "focusHistoryID", default)]
+    pub focus_history_id: Option<i64>,
+    /// Top-left corner in the global layout, in logical pixels.
+    #[serde(default)]
+    pub at: [i32; 2],
+    #[serde(default)]
+    pub size: [i32; 2],
+    /// Id of the monitor the window is on.
+    #[serde(default)]
+    pub monitor: i64,
+    #[serde(default)]
+    pub pid: i64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WorkspaceRef {
+    /// Regular workspaces count up from 1; special (scratchpad) workspaces are negative.
+    pub id: i64,
+    pub name: String,
+}
+
+/// `fullscreen` was a boolean before Hyprland 0.42 and a mode number since.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum Fullscreen {
+    Flag(bool),
+    Mode(u8),
+}
+
+impl Default for Fullscreen {
+    fn default() -> Self {
+        Fullscreen::Flag(false)
+    }
+}
+
+impl Fullscreen {
+    pub fn is_fullscreen(self) -> bool {
+        match self {
+            Fullscreen::Flag(flag) => flag,
+            Fullscreen::Mode(mode) => mode != 0,
+        }
+    }
+}
+
+/// An output as Hyprland's `monitors` request describes it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Monitor {
+    pub id: i64,
+    pub name: String,
+    x: i32,
+    y: i32,
+    /// Mode size in physical pixels, before transform and scale.
+    width: u32,
+    height: u32,
+    scale: f32,
+    /// `wl_output` transform; odd values rotate by 90 or 270 degrees.
+    #[serde(default)]
+    transform: u8,
+    #[serde(rename = "activeWorkspace", default)]
+    pub active_workspace: WorkspaceRef,
+    /// Whether the monitor has keyboard focus.
+    #[serde(default)]
+    pub focused: bool,
+    /// The special workspace shown over the active one; id 0 when none is open.
+    #[serde(rename = "specialWorkspace", default)]
+    pub special_workspace: WorkspaceRef,
+}
+
+impl Monitor {
+    /// Whether the workspace is on screen here, either as the active one or as the open
+    /// special workspace.
+    pub fn shows(&self, workspace: i64) -> bool {
+        workspace == self.active_workspace.id
+            || (self.special_workspace.id != 0 && workspace == self.special_workspace.id)
+    }
+
+    /// The area the monitor covers in the global layout, in logical pixels, like
+    /// [`Client::rect`].
+    pub fn rect(&self) -> Rectangle {
+        let (width, height) = if self.transform % 2 == 1 {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        let scale = if self.scale > 0.0 { self.scale } else { 1.0 };
+        Rectangle {
+            x: self.x as f32,
+            y: self.y as f32,
+            width: width as f32 / scale,
+            height: height as f32 / scale,
+        }
+    }
+}
+
+impl Client {
+    pub fn rect(&self) -> Rectangle {
+        Rectangle {
+            x: self.at[0] as f32,
+            y: self.at[1] as f32,
+            width: self.size[0] as f32,
+            height: self.size[1] as f32,
+        }
+    }
+
+    /// The window address as a number. XDPH lists it as each window's mapped id, after `[HE>]`.
+    pub fn id(&self) -> Option<u64> {
+        u64::from_str_radix(self.address.strip_prefix("0x")?, 16).ok()
+    }
+}
+
+#[derive(Debug)]
+pub enum IpcError {
+    /// `HYPRLAND_INSTANCE_SIGNATURE` is unset, e.g. when testing outside Hyprland.
+    NotRunning,
+    Io(io::Error),
+    Parse(serde_json::Error),
+    /// Hyprland refused a dispatch; its reply says why.
+    Rejected(String),
+}
+
+impl fmt::Display for IpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpcError::NotRunning => write!(f, "HYPRLAND_INSTANCE_SIGNATURE is not set"),
+            IpcError::Io(error) => write!(f, "socket error: {error}"),
+            IpcError::Parse(error) => write!(f, "unexpected reply: {error}"),
+            IpcError::Rejected(reply) => write!(f, "rejected: {reply}"),
+        }
+    }
+}
+
+impl std::error::Error for IpcError {}
+
+/// Every mapped window, with its workspace.
+pub fn clients() -> Result<Vec<Client>, IpcError> {
+    let reply = request("j/clients")?;
+    serde_json::from_slice(&reply).map_err(IpcError::Parse)
+}
+
+/// The client behind each portal window, in the portal's order. They are paired by mapped id:
+/// the portal's own handles are foreign-toplevel ids of XDPH's and unrelated to addresses.
+pub fn for_portal_windows(windows: &[PortalWindow], clients: Vec<Client>) -> Vec<Option<Client>> {
+    let mut clients: HashMap<u64, Client> = clients
+        .into_iter()
+        .filter_map(|client| Some((client.id()?, client)))
+        .collect();
+    windows
+        .iter()
+        .map(|window| clients.remove(&window.mapped_id))
+        .collect()
+}
+
+/// Every connected output.
+pub fn monitors() -> Result<Vec<Monitor>, IpcError> {
+    let reply = request("j/monitors")?;
+    serde_json::from_slice(&reply).map_err(IpcError::Parse)
+}
+
+/// Runs a dispatcher, such as `focuswindow address:0x55d0c6b1e2a0`.
+pub fn dispatch(dispatcher: &str) -> Result<(), IpcError> {
+    let reply = request(&format!("dispatch {dispatcher}"))?;
+    let reply = String::from_utf8_lossy(&reply);
+    match reply.trim() {
+        "ok" => Ok(()),
+        reply => Err(IpcError::Rejected(reply.to_string())),
+    }
+}
+
+/// Focuses this process's own window again, bringing it to the front and its workspace on
+/// screen, e.g. after activating another window. Does nothing if Hyprland doesn't list it.
+pub fn focus_own_window() -> Result<(), IpcError> {
+    let pid = i64::from(std::process::id());
+    let Some(own) = clients()?.into_iter().find(|client| client.pid == pid) else {
+        return Ok(());
+    };
+    dispatch(&format!("focuswindow address:{}", own.address))
+}
+
+/// Sends one request over the command socket and reads the whole reply.
+fn request(command: &str) -> Result<Vec<u8>, IpcError> {
+    let mut stream = UnixStream::connect(socket_path()?).map_err(IpcError::Io)?;
+    stream.set_read_timeout(Some(IPC_TIMEOUT)).map_err(IpcError::Io)?;
+    stream.set_write_timeout(Some(IPC_TIMEOUT)).map_err(IpcError::Io)?;
+    stream.write_all(command.as_bytes()).map_err(IpcError::Io)?;
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).map_err(IpcError::Io)?;
+    Ok(reply)
+}
+
+/// `$XDG_RUNTIME_DIR/hypr/<signature>/.socket.sock`, or `/tmp/hypr/...` on Hyprland
+/// releases from before the move.
+fn socket_path() -> Result<PathBuf, IpcError> {
+    let signature = std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE")
+        .filter(|value| !value.is_empty())
+        .ok_or(IpcError::NotRunning)?;
+    let runtime = std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .map(|dir| dir.join("hypr").join(&signature).join(".socket.sock"));
+    Ok(runtime
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from("/tmp/hypr").join(&signature).join(".socket.sock")))
+}
+
+/// Windows and monitors for tests across the crate. Tests override fields with struct update
+/// syntax, e.g. `Client { floating: true, ..client("0x1", 2) }`.
+#[cfg(test)]
+pub mod fixtures {
+    use super::*;
+
+    /// A 400×300 tiled window at the origin of the global layout, on monitor 0.
+    pub fn client(address: &str, workspace: i64) -> Client {
+        Client {
+            address: address.to_string(),
+            workspace: workspace_ref(workspace),
+            floating: false,
+            pinned: false,
+            fullscreen: Fullscreen::default(),
+            focus_history_id: None,
+            at: [0, 0],
+            size: [400, 300],
+            monitor: 0,
+            pid: 0,
+        }
+    }
+
+    /// A 1920×1080 monitor at scale 1, `x` pixels from the left, showing workspace `active`.
+    pub fn monitor(id: i64, name: &str, x: i32, active: i64) -> Monitor {
+        Monitor {
+            id,
+            name: name.to_string(),
+            x,
+            y: 0,
+            width: 1920,
+            height: 1080,
+            scale: 1.0,
+            transform: 0,
+            active_workspace: workspace_ref(active),
+            focused: false,
+            special_workspace: WorkspaceRef::default(),
+        }
+    }
+
+    /// Workspace `id` named the way Hyprland names it; -98 is the `magic` scratchpad.
+    pub fn workspace_ref(id: i64) -> WorkspaceRef {
+        let name = if id == -98 {
+            "special:magic".to_string()
+        } else {
+            id.to_string()
+        };
+        WorkspaceRef { id, name }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portal;
+
+    #[test]
+    fn parses_clients() {
+        let reply = r#"[{
+            "address": "0x55d0c6b1e2a0",
+            "mapped": true,
+            "workspace": { "id": 2, "name": "2" },
+            "floating": true,
+            "fullscreen": 2,
+            "class": "firefox",
+            "title": "Example",
+            "focusHistoryID": 1
+        }, {
+            "address": "0x1",
+            "workspace": { "id": -98, "name": "special:magic" },
+            "fullscreen": false
+        }]"#;
+        let clients: Vec<Client> = serde_json::from_str(reply).unwrap();
+        assert_eq!(clients[0].workspace.name, "2");
+        assert_eq!(clients[0].id(), Some(0x55d0_c6b1_e2a0));
+        assert!(clients[0].floating && clients[0].fullscreen.is_fullscreen());
+        assert_eq!(clients[0].focus_history_id, Some(1));
+        assert!(!clients[1].fullscreen.is_fullscreen());
+    }
+
+    #[test]
+    fn pairs_portal_windows_with_clients_by_mapped_id() {
+        // As XDPH lists them: its own handle first, the Hyprland address in decimal last.
+        let list = "1834059424[HC>]kitty[HT>]~[HE>]94355175105184[HA>]\
+                    1834061232[HC>]kitty[HT>]~[HE>]94355175109392[HA>]\
+                    1834063040[HC>]firefox[HT>]Example[HE>]0[HA>]";
+        let windows = portal::parse_window_list(list);
+        let clients = vec![
+            fixtures::client("0x55d0c6b1f310", 3),
+            fixtures::client("0x55d0c6b1e2a0", 2),
+        ];
+        assert_ne!(u64::from(windows[0].handle_lo), windows[0].mapped_id & 0xffff_ffff);
+        let paired = for_portal_windows(&windows, clients);
+        let workspaces: Vec<Option<i64>> = paired
+            .iter()
+            .map(|client| client.as_ref().map(|client| client.workspace.id))
+            .collect();
+        assert_eq!(workspaces, [Some(2), Some(3), None]);
+    }
+
+    #[test]
+    fn monitor_rects_are_logical() {
+        let reply = r#"[
+            { "id": 0, "name": "DP-1", "x": 0, "y": 0, "width": 3840, "height": 2160,
+              "scale": 1.5, "transform": 0 },
+            { "id": 1, "name": "HDMI-A-1", "x": 2560, "y": 0, "width": 1920, "height": 1080,
+              "scale": 1.0, "transform": 1,
+              "activeWorkspace": { "id": 2, "name": "2" },
+              "specialWorkspace": { "id": -98, "name": "special:magic" } }
+        ]"#;
+        let monitors: Vec<Monitor> = serde_json::from_str(reply).unwrap();
+        assert_eq!(
+            monitors[0].rect(),
+            Rectangle::new([0.0, 0.0].into(), [2560.0, 1440.0].into())
+        );
+        assert_eq!(
+            monitors[1].rect(),
+            Rectangle::new([2560.0, 0.0].into(), [1080.0, 1920.0].into())
+        );
+        assert!(monitors[1].shows(2) && monitors[1].shows(-98) && !monitors[1].shows(1));
+        assert!(!monitors[0].shows(-98));
+    }
+}
@@ -1,15 +1,81 @@
+mod config;
+mod convert;
+mod desktop;
+mod event_queue;
+mod highlight;
+mod history;
+mod hyprland;
+mod memory;
+mod overlay;
+mod overview;
+mod point_select;
+mod portal;
+mod resample;
+mod scheduler;
+mod share_actions;
+mod sort;
+mod state;
 mod wayland;
+mod widget;
 
-use iced::widget::{button, column, container, image, row, scrollable, text};
-use iced::{keyboard, Alignment, Element, Event, Length, Subscription, Task};
+use config::Backdrop;
+use desktop::{AppInfo, Icon};
+use history::History;
+use iced::widget::{
+    button, canvas, column, container, image, mouse_area, pick_list, pin, responsive, row,
+    scrollable, sensor, space, stack, svg, text, toggler, tooltip,
+};
+use iced::futures::channel::oneshot;
+use iced::{
+    keyboard, mouse, window, Alignment, Border, Element, Event, Length, Point, Size, Subscription,
+    Task,
+};
+use state::{Layout, SortMode, UiState, MAX_TILE_ZOOM, MIN_TILE_ZOOM};
+use std::collections::{HashMap, HashSet};
 use std::io::{self, Write};
-use wayland::{WaylandEvent, WindowThumbnail};
+use std::time::{Duration, Instant};
+use wayland::{
+    CaptureError, ThumbnailSizes, WaylandCommand, WaylandEvent, WaylandHandle, WindowKey,
+    WindowThumbnail,
+};
 
 #[derive(Debug, Clone)]
 enum Message {
     Wayland(WaylandEvent),
     Select(u32),
+    /// Pre-share actions are done; the window is handed to the portal.
+    Selected(u32),
     UiEvent(Event),
+    /// Advances the capture spinner, while any capture is pending.
+    SpinnerTick,
+    /// Timer clock, running while a zoom delay or a resize settle is pending.
+    Tick(Instant),
+    /// A top-level tile scrolled into (or near) the viewport, by `handle_lo`.
+    TileShown(u32),
+    TileHidden(u32),
+    /// The pointer entered or left a top-level tile, by `handle_lo`.
+    TileHovered(u32),
+    TileUnhovered(u32),
+    WindowOpened(window::Id),
+    ScaleFactorChanged(f32),
+    OverlayCursorToggled(bool),
+    LayoutChanged(Layout),
+    SortChanged(SortMode),
+    /// Shows an overview page, by index.
+    OverviewPage(usize),
+    /// Pins or unpins an application class to the Recent row.
+    TogglePin(String),
+    ClearHistory,
+    /// Focuses the window in the compositor to have a look at it; the picker stays open.
+    JumpTo(u32),
+    /// Opens the on-screen overlay to click the window to share.
+    PickOnScreen,
+    /// The overlay closed: the window clicked, or `None` if it was dismissed.
+    PickedOnScreen(Result<Option<u32>, String>),
+    /// Desktop entries found for the listed window classes, by class.
+    AppsResolved(HashMap<String, AppInfo>),
+    /// Mouse wheel input, including scrolls the grid consumed; with Ctrl held it resizes tiles.
+    WheelScrolled(mouse::ScrollDelta),
     CloseRequested,
 }
 
@@ -18,15 +84,125 @@ struct WindowEntry {
     handle_lo: u32,
     class: String,
     title: String,
+    /// The window's Hyprland address, as the portal lists it.
     mapped_id: u64,
     group_index: usize,
     group_size: usize,
-    thumbnail: Option<WindowThumbnail>,
+    capture: CaptureState,
+    parent: Option<u32>,
+    /// Position in the portal's list.
+    portal_index: usize,
+    /// Hyprland's view of the window, when Hyprland could be asked.
+    client: Option<hyprland::Client>,
+    /// Serial of the last time the window gained focus while the picker was open.
+    activation: Option<u64>,
+    /// Last time the tile was in or near the viewport; eviction drops the stalest first.
+    last_visible: Option<Instant>,
+}
+
+/// Where a tile's preview stands, so each failure mode gets its own placeholder.
+#[derive(Debug, Clone)]
+enum CaptureState {
+    /// Waiting for the compositor to deliver a frame.
+    Pending,
+    Ready(WindowThumbnail),
+    Failed(CaptureError),
+    /// No foreign-toplevel matched this portal entry, so there is nothing to capture.
+    NoMatch,
+    /// A config rule keeps this window's contents out of the picker.
+    Hidden,
+    /// The thumbnail was dropped to stay within the memory budget; recaptured when shown.
+    Evicted,
+}
+
+impl WindowEntry {
+    fn matches(&self, key: &WindowKey) -> bool {
+        self.class == key.app_id
+            && self.title == key.title
+            && self.group_index == key.group_index
+            && self.group_size == key.group_size
+    }
+
+    /// Replaces the capture state, keeping the memory stats in step with the thumbnail.
+    fn set_capture(&mut self, capture: CaptureState) {
+        if let CaptureState::Ready(thumbnail) = &capture {
+            memory::stats().add_thumbnail(thumbnail.byte_len());
+        }
+        if let CaptureState::Ready(thumbnail) = std::mem::replace(&mut self.capture, capture) {
+            memory::stats().remove_thumbnail(thumbnail.byte_len());
+        }
+    }
+
+    fn sort_fields<'a>(&'a self, apps: &'a HashMap<String, AppInfo>) -> sort::SortFields<'a> {
+        sort::SortFields {
+            portal_index: self.portal_index,
+            title: &self.title,
+            app: apps.get(&self.class).map_or(&self.class, |app| &app.name),
+            activation: self.activation,
+            client: self.client.as_ref(),
+        }
+    }
+
+    fn key(&self) -> WindowKey {
+        WindowKey {
+            app_id: self.class.clone(),
+            title: self.title.clone(),
+            group_index: self.group_index,
+            group_size: self.group_size,
+        }
+    }
 }
 
 struct App {
     windows: Vec<WindowEntry>,
     allow_token: bool,
+    /// Whether thumbnails include the cursor.
+    overlay_cursor: bool,
+    /// Toplevels announced by the compositor, as `(app_id, title)` by protocol id.
+    toplevels: HashMap<u32, (String, String)>,
+    toplevels_enumerated: bool,
+    spinner_frame: usize,
+    wayland: Option<WaylandHandle>,
+    /// Top-level tiles currently in or near the viewport, by `handle_lo`.
+    visible_tiles: HashSet<u32>,
+    /// Display scale of the picker window, once known; thumbnails are rendered at physical size.
+    scale_factor: Option<f32>,
+    /// Top-level tile under the pointer, by `handle_lo`.
+    hovered: Option<u32>,
+    /// Top-level tile selected with the keyboard, by `handle_lo`.
+    focused: Option<u32>,
+    /// Tile waiting to be shown in the zoom pane, and since when.
+    zoom_target: Option<(u32, Instant)>,
+    /// Tile shown in the zoom pane. It stays until another one is hovered or focused.
+    zoomed: Option<u32>,
+    /// Layout and tile size, saved between launches.
+    ui: UiState,
+    /// Names and icons from desktop entries, by window class. Empty until resolved.
+    apps: HashMap<String, AppInfo>,
+    /// Source of [`WindowEntry::activation`] serials.
+    activations: u64,
+    history: History,
+    /// When the picker started, in Unix seconds; frecency is scored against it.
+    launched_at: u64,
+    /// Outputs as Hyprland reports them; empty without IPC.
+    monitors: Vec<hyprland::Monitor>,
+    overview_page: usize,
+    modifiers: keyboard::Modifiers,
+    /// Touchpad scrolling left over from the last Ctrl+scroll resize step.
+    wheel_pixels: f32,
+    /// When the tile size last changed; thumbnails are regenerated once it settles.
+    resize_pending: Option<Instant>,
+    /// Whether the point-and-click overlay is up.
+    picking_on_screen: bool,
+    /// Whether a window was chosen and its pre-share actions are running.
+    sharing: bool,
+    /// Outlines windows on screen, unless disabled in the config.
+    highlight: Option<highlight::HighlightHandle>,
+    /// The window last outlined.
+    highlighted: Option<u32>,
+    /// Indices into `windows` of the Recent row's tiles and of the other tiles, in display
+    /// order; see [`App::update_sections`].
+    sections: (Vec<usize>, Vec<usize>),
 }
 
 fn main() -> iced::Result {
@@ -37,13 +213,61 @@ fn main() -> iced::Result {
 
 impl App {
     fn new() -> (Self, Task<Message>) {
-        (
-            Self {
-                windows: parse_window_list(),
-                allow_token: std::env::args().any(|arg| arg == "--allow-token"),
-            },
-            Task::none(),
-        )
+        let windows = parse_window_list();
+
+        // Scanning desktop entries and icon themes touches a lot of files; keep it off the UI
+        // thread and fill names and icons in when done.
+        let classes: Vec<String> = windows.iter().map(|window| window.class.clone()).collect();
+        let (sender, receiver) = oneshot::channel();
+        rayon::spawn(move || {
+            let _ = sender.send(desktop::resolve(classes.iter().map(String::as_str)));
+        });
+        let resolve_apps =
+            Task::perform(receiver, |apps| Message::AppsResolved(apps.unwrap_or_default()));
+
+        let mut app = Self {
+            windows,
+            allow_token: std::env::args().any(|arg| arg == "--allow-token"),
+            overlay_cursor: config::get().overlay_cursor
+                || std::env::args().any(|arg| arg == "--overlay-cursor"),
+            toplevels: HashMap::new(),
+            toplevels_enumerated: false,
+            spinner_frame: 0,
+            wayland: None,
+            visible_tiles: HashSet::new(),
+            scale_factor: None,
+            hovered: None,
+            focused: None,
+            zoom_target: None,
+            zoomed: None,
+            ui: state::load(),
+            apps: HashMap::new(),
+            activations: 0,
+            history: history::load(),
+            launched_at: history::now(),
+            monitors: hyprland::monitors().unwrap_or_else(|error| {
+                wayland::debug_log(&format!("hyprland: can't list monitors: {error}"));
+                Vec::new()
+            }),
+            overview_page: 0,
+            modifiers: keyboard::Modifiers::default(),
+            wheel_pixels: 0.0,
+            resize_pending: None,
+            picking_on_screen: false,
+            sharing: false,
+            highlight: config::get().highlight_windows.then(highlight::spawn),
+            highlighted: None,
+            sections: (Vec::new(), Vec::new()),
+        };
+        app.sort_windows();
+        let pick_on_screen = if config::get().pick_on_screen
+            || std::env::args().any(|arg| arg == "--pick-on-screen")
+        {
+            app.update(Message::PickOnScreen)
+        } else {
+            Task::none()
+        };
+        (app, Task::batch([resolve_apps, pick_on_screen]))
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
@@ -52,15 +276,250 @@ impl App {
                 self.apply_wayland_event(event);
             }
             Message::UiEvent(event) => {
-                if let Event::Keyboard(keyboard::Event::KeyPressed { key, .. }) = event {
-                    if key == keyboard::Key::Named(keyboard::key::Named::Escape) {
+                match event {
+                    Event::Keyboard(keyboard::Event::KeyPressed {
+                        key: keyboard::Key::Named(keyboard::key::Named::Escape),
+                        ..
+                    }) => {
                         std::process::exit(1);
                     }
+                    Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                        self.modifiers = modifiers;
+                    }
+                    Event::Keyboard(keyboard::Event::KeyPressed {
+                        key: keyboard::Key::Character(character),
+                        modifiers,
+                        ..
+                    }) if modifiers.command() => match character.as_str() {
+                        "+" | "=" => self.step_tile_zoom(1),
+                        "-" => self.step_tile_zoom(-1),
+                        "0" => self.set_tile_zoom(100),
+                        "s" => return self.update(Message::SortChanged(self.ui.sort.next())),
+                        "p" => {
+                            if let Some(window) = self
+                                .focused
+                                .and_then(|focused| self.windows.iter().find(|w| w.handle_lo == focused))
+                            {
+                                return self.update(Message::TogglePin(window.class.clone()));
+                            }
+                        }
+                        _ => {}
+                    },
+                    Event::Keyboard(keyboard::Event::KeyPressed {
+                        key: keyboard::Key::Named(named),
+                        modifiers,
+                        ..
+                    }) => {
+                        use keyboard::key::Named;
+                        match named {
+                            Named::Tab if modifiers.shift() => return self.move_focus(-1),
+                            Named::Tab | Named::ArrowRight | Named::ArrowDown => {
+                                return self.move_focus(1);
+                            }
+                            Named::ArrowLeft | Named::ArrowUp => return self.move_focus(-1),
+                            Named::PageDown | Named::PageUp if self.ui.layout == Layout::Overview => {
+                                let pages = self.overview_pages().len().max(1);
+                                let step = if named == Named::PageDown { 1 } else { pages - 1 };
+                                self.overview_page = (self.overview_page + step) % pages;
+                            }
+                            Named::Enter => {
+                                if let Some(focused) = self.focused {
+                                    return self.update(if modifiers.command() {
+                                        Message::JumpTo(focused)
+                                    } else {
+                                        Message::Select(focused)
+                                    });
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    Event::Window(window::Event::Rescaled(scale_factor)) => {
+                        return self.update(Message::ScaleFactorChanged(scale_factor));
+                    }
+                    _ => {}
+                }
+            }
+            Message::SpinnerTick => {
+                self.spinner_frame = (self.spinner_frame + 1) % SPINNER.len();
+            }
+            Message::Tick(now) => {
+                if let Some((handle_lo, since)) = self.zoom_target
+                    && now.saturating_duration_since(since) >= ZOOM_DELAY
+                {
+                    self.zoomed = Some(handle_lo);
+                    self.zoom_target = None;
+                }
+                if let Some(since) = self.resize_pending
+                    && now.saturating_duration_since(since) >= RESIZE_SETTLE
+                {
+                    self.resize_pending = None;
+                    self.report_thumbnail_size();
+                    state::save(&self.ui);
+                }
+            }
+            Message::LayoutChanged(layout) => {
+                if self.ui.layout != layout {
+                    self.ui.layout = layout;
+                    self.resize_pending = Some(Instant::now());
+                    self.update_sections();
+                    // Compact tiles pull children out of their parents, which changes what
+                    // is on screen.
+                    self.report_visible();
+                }
+            }
+            Message::AppsResolved(apps) => {
+                self.apps = apps;
+                // Application names are what the class sort compares.
+                self.sort_windows();
+            }
+            Message::TogglePin(class) => {
+                self.history.toggle_pin(&class);
+                history::save(&self.history);
+                self.update_sections();
+            }
+            Message::ClearHistory => {
+                self.history.clear_shares();
+                history::save(&self.history);
+                self.update_sections();
+            }
+            Message::JumpTo(id) => {
+                if let (Some(wayland), Some(window)) =
+                    (&self.wayland, self.windows.iter().find(|w| w.handle_lo == id))
+                {
+                    wayland.send(WaylandCommand::Activate(window.key()));
+                }
+            }
+            Message::PickOnScreen => {
+                if self.picking_on_screen {
+                    return Task::none();
+                }
+                self.picking_on_screen = true;
+                let candidates = self
+                    .windows
+                    .iter()
+                    .map(|window| (window.mapped_id, window.handle_lo))
+                    .collect();
+                // The overlay blocks on its own Wayland connection until it is dismissed, so it
+                // gets a thread of its own rather than a rayon worker.
+                let (sender, receiver) = oneshot::channel();
+                std::thread::spawn(move || {
+                    let result = point_select::run(candidates).map_err(|error| error.to_string());
+                    let _ = sender.send(result);
+                });
+                return Task::perform(receiver, |result| {
+                    Message::PickedOnScreen(
+                        result.unwrap_or_else(|_| Err("overlay stopped unexpectedly".to_string())),
+                    )
+                });
+            }
+            Message::PickedOnScreen(result) => {
+                self.picking_on_screen = false;
+                match result {
+                    // Hyprland addresses were matched to portal handles before the overlay
+                    // opened, but the list may have changed since.
+                    Ok(Some(id)) if self.windows.iter().any(|w| w.handle_lo == id) => {
+                        return self.update(Message::Select(id));
+                    }
+                    // Dismissed: the grid is still there.
+                    Ok(_) => {}
+                    Err(error) => eprintln!("Point-and-click error: {error}"),
+                }
+            }
+            Message::OverviewPage(page) => {
+                self.overview_page = page;
+            }
+            Message::SortChanged(sort) => {
+                if self.ui.sort != sort {
+                    self.ui.sort = sort;
+                    self.sort_windows();
+                    state::save(&self.ui);
+                }
+            }
+            Message::WheelScrolled(delta) => {
+                if self.modifiers.command() {
+                    let steps = match delta {
+                        mouse::ScrollDelta::Lines { y, .. } => y.signum() as i32,
+                        mouse::ScrollDelta::Pixels { y, .. } => {
+                            self.wheel_pixels += y;
+                            let steps = (self.wheel_pixels / WHEEL_PIXELS_PER_STEP).trunc();
+                            self.wheel_pixels -= steps * WHEEL_PIXELS_PER_STEP;
+                            steps as i32
+                        }
+                    };
+                    self.step_tile_zoom(steps);
+                }
+            }
+            Message::TileShown(handle_lo) => {
+                if self.visible_tiles.insert(handle_lo) {
+                    self.report_visible();
+                }
+            }
+            Message::TileHidden(handle_lo) => {
+                if self.visible_tiles.remove(&handle_lo) {
+                    self.report_visible();
+                }
+            }
+            Message::TileHovered(handle_lo) => {
+                self.hovered = Some(handle_lo);
+                self.set_zoom_target(Some(handle_lo));
+            }
+            Message::TileUnhovered(handle_lo) => {
+                if self.hovered == Some(handle_lo) {
+                    self.hovered = None;
+                    // Fall back to the keyboard focus, if any.
+                    self.set_zoom_target(self.focused);
+                }
+            }
+            Message::WindowOpened(id) => {
+                return window::scale_factor(id).map(Message::ScaleFactorChanged);
+            }
+            Message::ScaleFactorChanged(scale_factor) => {
+                self.scale_factor = Some(scale_factor);
+                self.report_thumbnail_size();
+            }
+            Message::OverlayCursorToggled(overlay_cursor) => {
+                self.overlay_cursor = overlay_cursor;
+                // Ready tiles keep their current thumbnail until the new one arrives.
+                for window in &mut self.windows {
+                    if matches!(window.capture, CaptureState::Failed(_)) {
+                        window.set_capture(CaptureState::Pending);
+                    }
+                }
+                if let Some(wayland) = &self.wayland {
+                    wayland.send(WaylandCommand::SetOverlayCursor(overlay_cursor));
                 }
             }
             Message::Select(id) => {
+                if std::mem::replace(&mut self.sharing, true) {
+                    return Task::none();
+                }
+                if config::get().share_history
+                    && let Some(window) = self.windows.iter().find(|w| w.handle_lo == id)
+                {
+                    self.history.record(&window.class, &window.title, history::now());
+                    history::save(&self.history);
+                }
+                // The portal starts streaming as soon as it reads the selection, so the window
+                // has to be on screen by then.
+                if let Some(window) = self.windows.iter().find(|w| w.handle_lo == id) {
+                    let actions = config::get().share_actions_for(&window.class, &window.title);
+                    if !actions.is_empty() {
+                        // Hyprland IPC blocks, so the UI keeps drawing on its own thread.
+                        let (actions, mapped_id) = (actions.to_vec(), window.mapped_id);
+                        let (sender, receiver) = oneshot::channel();
+                        std::thread::spawn(move || {
+                            share_actions::run(&actions, mapped_id);
+                            let _ = sender.send(());
+                        });
+                        return Task::perform(receiver, move |_| Message::Selected(id));
+                    }
+                }
+                return self.update(Message::Selected(id));
+            }
+            Message::Selected(id) => {
                 let flags = if self.allow_token { "r" } else { "" };
-                print!("[SELECTION]{}/window:{}\n", flags, id);
+                println!("[SELECTION]{}/window:{}", flags, id);
                 let _ = io::stdout().flush();
                 std::process::exit(0);
             }
@@ -74,23 +533,70 @@ impl App {
 
     fn apply_wayland_event(&mut self, event: WaylandEvent) {
         match event {
-            WaylandEvent::Upsert { .. } | WaylandEvent::Remove { .. } => {}
+            WaylandEvent::Connected(handle) => {
+                // Before the thumbnail size, which is what lets the first captures start.
+                handle.send(WaylandCommand::SetOverlayCursor(self.overlay_cursor));
+                self.wayland = Some(handle);
+                self.report_thumbnail_size();
+                self.report_visible();
+            }
+            WaylandEvent::Upsert { id, title, app_id } => {
+                self.toplevels.insert(id, (app_id, title));
+                if self.toplevels_enumerated {
+                    self.mark_unmatched_windows();
+                }
+            }
+            WaylandEvent::Remove { id } => {
+                self.toplevels.remove(&id);
+            }
+            WaylandEvent::ToplevelsEnumerated => {
+                self.toplevels_enumerated = true;
+                self.mark_unmatched_windows();
+            }
             WaylandEvent::Thumbnail {
-                title,
-                app_id,
-                group_index,
-                group_size,
+                key,
                 width,
                 height,
                 rgba,
+                zoom,
             } => {
-                if let Some(existing) = self.windows.iter_mut().find(|w| {
-                    w.class == app_id
-                        && w.title == title
-                        && w.group_index == group_index
-                        && w.group_size == group_size
-                }) {
-                    existing.thumbnail = Some(WindowThumbnail::new(width, height, rgba));
+                if let Some(existing) = self.windows.iter_mut().find(|w| w.matches(&key))
+                    && !matches!(existing.capture, CaptureState::Hidden)
+                {
+                    existing.set_capture(CaptureState::Ready(
+                        WindowThumbnail::new(width, height, rgba).with_zoom(zoom),
+                    ));
+                }
+                self.enforce_memory_budget();
+            }
+            WaylandEvent::CaptureFailed { key, error } => {
+                if let Some(existing) = self.windows.iter_mut().find(|w| w.matches(&key))
+                    && !matches!(existing.capture, CaptureState::Hidden)
+                {
+                    existing.set_capture(CaptureState::Failed(error));
+                }
+            }
+            WaylandEvent::Parent { child, parent } => {
+                let parent = parent.and_then(|parent| {
+                    self.windows
+                        .iter()
+                        .find(|w| w.matches(&parent))
+                        .map(|w| w.handle_lo)
+                });
+                if let Some(existing) = self.windows.iter_mut().find(|w| w.matches(&child)) {
+                    existing.parent = parent.filter(|parent| *parent != existing.handle_lo);
+                }
+                self.update_sections();
+                // Nesting moves a child under its parent's tile, and with it its visibility.
+                self.report_visible();
+            }
+            WaylandEvent::Activated { key } => {
+                if let Some(existing) = self.windows.iter_mut().find(|w| w.matches(&key)) {
+                    self.activations += 1;
+                    existing.activation = Some(self.activations);
+                    if self.ui.sort == SortMode::RecentFocus {
+                        self.sort_windows();
+                    }
                 }
             }
             WaylandEvent::Error { message } => {
@@ -99,133 +605,975 @@ impl App {
         }
     }
 
-    fn view(&self) -> Element<Message> {
-        let mut tiles = row!().spacing(16);
+    fn report_thumbnail_size(&self) {
+        let (Some(wayland), Some(scale_factor)) = (&self.wayland, self.scale_factor) else {
+            return;
+        };
+        let physical = |logical: Size| {
+            Size::new(
+                (logical.width * scale_factor).ceil() as u32,
+                (logical.height * scale_factor).ceil() as u32,
+            )
+        };
+        wayland.send(WaylandCommand::SetThumbnailSize(ThumbnailSizes {
+            grid: physical(self.thumbnail_bounds()),
+            zoom: config::get()
+                .zoom_pane
+                .then(|| physical(Size::new(ZOOM_WIDTH, ZOOM_HEIGHT))),
+        }));
+    }
 
-        for window in &self.windows {
-            let thumb: Element<_> = if let Some(thumbnail) = &window.thumbnail {
-                image(thumbnail.handle.clone())
-                    .width(Length::Fixed(220.0))
-                    .height(Length::Fixed(140.0))
-                    .into()
-            } else {
-                let placeholder = container(text("No preview").size(14))
-                    .width(Length::Fixed(220.0))
-                    .height(Length::Fixed(140.0))
-                    .center_x(Length::Fill)
-                    .center_y(Length::Fill);
-                placeholder.into()
-            };
+    /// Puts `windows`, and with them the tiles, in the selected order. The sort is stable and
+    /// falls back to portal order, so equal windows never swap places between launches.
+    fn sort_windows(&mut self) {
+        let (apps, mode) = (&self.apps, self.ui.sort);
+        self.windows
+            .sort_by(|a, b| sort::compare(mode, &a.sort_fields(apps), &b.sort_fields(apps)));
+        self.update_sections();
+    }
+
+    /// Logical size of a tile's picture area in the current layout and zoom.
+    fn thumbnail_bounds(&self) -> Size {
+        let base = match self.ui.layout {
+            Layout::Grid => Size::new(TILE_WIDTH, TILE_HEIGHT),
+            Layout::List => Size::new(LIST_THUMBNAIL_WIDTH, LIST_THUMBNAIL_HEIGHT),
+            Layout::Compact => Size::new(COMPACT_WIDTH, COMPACT_HEIGHT),
+            // Windows drawn larger than this use the zoom pane's copy.
+            Layout::Overview => Size::new(TILE_WIDTH, TILE_HEIGHT),
+        };
+        base * (self.ui.tile_zoom as f32 / 100.0)
+    }
+
+    fn step_tile_zoom(&mut self, steps: i32) {
+        let zoom = self.ui.tile_zoom as i32 + steps * TILE_ZOOM_STEP as i32;
+        self.set_tile_zoom(zoom.max(0) as u32);
+    }
+
+    /// Resizes tiles at once; the old thumbnails are stretched until the size settles and
+    /// they are regenerated, so a burst of Ctrl+scroll costs one round of captures.
+    fn set_tile_zoom(&mut self, zoom: u32) {
+        let zoom = zoom.clamp(MIN_TILE_ZOOM, MAX_TILE_ZOOM);
+        if self.ui.tile_zoom != zoom {
+            self.ui.tile_zoom = zoom;
+            self.resize_pending = Some(Instant::now());
+        }
+    }
+
+    /// Moves the keyboard focus `step` tiles on, scrolling the grid to keep it in view.
+    fn move_focus(&mut self, step: isize) -> Task<Message> {
+        let tiles: Vec<u32> = self.tiles().iter().map(|w| w.handle_lo).collect();
+        if tiles.is_empty() {
+            return Task::none();
+        }
+        let next = match self.focused.and_then(|f| tiles.iter().position(|&h| h == f)) {
+            Some(current) => (current as isize + step).rem_euclid(tiles.len() as isize) as usize,
+            None if step < 0 => tiles.len() - 1,
+            None => 0,
+        };
+        self.focused = Some(tiles[next]);
+        self.set_zoom_target(self.focused);
+        // Follow the focus onto its overview page.
+        if let Some(window) = self.windows.iter().find(|w| w.handle_lo == tiles[next])
+            && let Some(page) = self
+                .overview_pages()
+                .iter()
+                .position(|page| page.contains(window.client.as_ref()))
+        {
+            self.overview_page = page;
+        }
+        widget::scroll_into_view(GRID_SCROLLABLE.into(), tile_id(tiles[next]))
+    }
+
+    /// Queues a tile for the zoom pane; it is shown once it has stayed the target for
+    /// [`ZOOM_DELAY`], so sweeping the pointer across the grid doesn't churn the pane.
+    fn set_zoom_target(&mut self, target: Option<u32>) {
+        if self.zoom_target.map(|(handle_lo, _)| handle_lo) != target {
+            self.zoom_target = target
+                .filter(|&handle_lo| self.zoomed != Some(handle_lo))
+                .map(|handle_lo| (handle_lo, Instant::now()));
+        }
+        // The outline follows right away; it is cheap and tells tiles apart at a glance.
+        if self.highlighted != target {
+            self.highlighted = target;
+            if let Some(highlight) = &self.highlight {
+                let window = target.and_then(|id| self.windows.iter().find(|w| w.handle_lo == id));
+                highlight.show(window.map(|window| window.mapped_id));
+            }
+        }
+    }
+
+    /// Tells the capture scheduler which windows are on screen (every visible tile plus the
+    /// children drawn inside it), and asks again for any of them that were evicted.
+    fn report_visible(&mut self) {
+        let now = Instant::now();
+        let visible: Vec<usize> = (0..self.windows.len())
+            .filter(|&i| self.visible_tiles.contains(&self.tile_of(&self.windows[i])))
+            .collect();
+        let mut keys = Vec::with_capacity(visible.len());
+        let mut evicted = Vec::new();
+        for i in visible {
+            let window = &mut self.windows[i];
+            window.last_visible = Some(now);
+            if matches!(window.capture, CaptureState::Evicted) {
+                window.set_capture(CaptureState::Pending);
+                evicted.push(window.key());
+            }
+            keys.push(window.key());
+        }
+
+        if let Some(wayland) = &self.wayland {
+            wayland.send(WaylandCommand::SetVisible(keys));
+            if !evicted.is_empty() {
+                wayland.send(WaylandCommand::Recapture(evicted));
+            }
+        }
+        self.enforce_memory_budget();
+    }
+
+    /// Drops thumbnails of off-screen tiles, least recently seen first, until pixel memory
+    /// fits the configured budget. Visible thumbnails are never evicted.
+    fn enforce_memory_budget(&mut self) {
+        while memory::stats().over_budget() {
+            let stalest = self
+                .windows
+                .iter()
+                .enumerate()
+                .filter(|(_, w)| {
+                    matches!(w.capture, CaptureState::Ready(_))
+                        && !self.visible_tiles.contains(&self.tile_of(w))
+                })
+                // Never-seen tiles (`None`) sort first.
+                .min_by_key(|(_, w)| w.last_visible)
+                .map(|(i, _)| i);
+            let Some(i) = stalest else { break };
+            wayland::debug_log(&format!(
+                "memory: evicting thumbnail of {:?}",
+                display_title(&self.windows[i])
+            ));
+            self.windows[i].set_capture(CaptureState::Evicted);
+        }
+    }
+
+    /// Settles tiles no toplevel can ever feed, and revives them if one shows up later.
+    fn mark_unmatched_windows(&mut self) {
+        for window in &mut self.windows {
+            let matched = self
+                .toplevels
+                .values()
+                .any(|(app_id, title)| *app_id == window.class && *title == window.title);
+            match window.capture {
+                CaptureState::Pending if !matched => window.capture = CaptureState::NoMatch,
+                CaptureState::NoMatch if matched => window.capture = CaptureState::Pending,
+                _ => {}
+            }
+        }
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let (recent, rest) = self.tile_sections();
+        let tiles: Element<_> = if self.ui.layout == Layout::Overview {
+            self.view_overview()
+        } else if recent.is_empty() {
+            self.view_section(rest)
+        } else {
+            column![
+                text("Recent").size(14),
+                self.view_section(recent),
+                text("All windows").size(14),
+                self.view_section(rest),
+            ]
+            .spacing(8)
+            .into()
+        };
+
+        let layout_button = |layout: Layout, label: &'static str| {
+            button(text(label).size(14))
+                .on_press(Message::LayoutChanged(layout))
+                .padding([4, 10])
+                .style(if self.ui.layout == layout {
+                    button::primary
+                } else {
+                    button::secondary
+                })
+        };
+        let toolbar = row![
+            toggler(self.overlay_cursor)
+                .label("Show cursor")
+                .on_toggle(Message::OverlayCursorToggled),
+            row![
+                layout_button(Layout::Grid, "Grid"),
+                layout_button(Layout::List, "List"),
+                layout_button(Layout::Compact, "Compact"),
+                layout_button(Layout::Overview, "Overview"),
+            ]
+            .spacing(4),
+            pick_list(SortMode::ALL, Some(self.ui.sort), Message::SortChanged).text_size(14),
+        ]
+        .push(
+            button(text("Pick on screen").size(14))
+                .on_press_maybe((!self.picking_on_screen).then_some(Message::PickOnScreen))
+                .padding([4, 10])
+                .style(button::secondary),
+        )
+        .push(self.history.has_shares().then(|| {
+            button(text("Clear history").size(14))
+                .on_press(Message::ClearHistory)
+                .padding([4, 10])
+                .style(button::secondary)
+        }))
+        .spacing(16)
+        .align_y(Alignment::Center);
+
+        let grid: Element<_> = if self.ui.layout == Layout::Overview {
+            // Pages are fitted to the window rather than scrolled.
+            tiles
+        } else {
+            scrollable(tiles)
+                .id(GRID_SCROLLABLE)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into()
+        };
+
+        let content: Element<_> = if config::get().zoom_pane {
+            row![grid, self.view_zoom_pane()].spacing(16).into()
+        } else {
+            grid
+        };
+
+        container(column![toolbar, content].spacing(12))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(16)
+            .into()
+    }
+
+    fn view_section<'a>(&'a self, windows: Vec<&'a WindowEntry>) -> Element<'a, Message> {
+        match self.ui.layout {
+            Layout::Grid => {
+                let mut tiles = row!().spacing(16);
+                for window in windows {
+                    tiles = tiles.push(self.view_grid_tile(window));
+                }
+                tiles.wrap().into()
+            }
+            Layout::List => {
+                let mut rows = column![].spacing(4).padding([0, 12]);
+                for window in windows {
+                    rows = rows.push(self.view_list_row(window));
+                }
+                rows.into()
+            }
+            Layout::Compact | Layout::Overview => {
+                let mut tiles = row!().spacing(8);
+                for window in windows {
+                    tiles = tiles.push(self.view_compact_tile(window));
+                }
+                tiles.wrap().into()
+            }
+        }
+    }
+
+    /// The current overview page: tabs for every workspace, then its monitors to scale with
+    /// the windows on top. Windows without geometry get plain compact tiles.
+    fn view_overview(&self) -> Element<'_, Message> {
+        let pages = self.overview_pages();
+        let Some(current) = pages.len().checked_sub(1).map(|last| self.overview_page.min(last))
+        else {
+            return text("No windows to show").into();
+        };
+        let page = pages[current].clone();
+
+        let mut tabs = row![].spacing(4);
+        for (index, tab) in pages.iter().enumerate() {
+            tabs = tabs.push(
+                button(text(tab.name.clone()).size(14))
+                    .on_press(Message::OverviewPage(index))
+                    .padding([4, 10])
+                    .style(if index == current {
+                        button::primary
+                    } else {
+                        button::secondary
+                    }),
+            );
+        }
+
+        let body: Element<_> = if page.workspace.is_none() || self.monitors.is_empty() {
+            let windows = self
+                .tiles()
+                .into_iter()
+                .filter(|window| page.contains(window.client.as_ref()))
+                .collect();
+            scrollable(self.view_section(windows))
+                .id(GRID_SCROLLABLE)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into()
+        } else {
+            responsive(move |area| self.view_overview_page(&page, area)).into()
+        };
+        column![tabs, body].spacing(12).into()
+    }
 
-            let title = if window.title.is_empty() {
-                "<untitled>"
+    fn view_overview_page(&self, page: &overview::Page, area: Size) -> Element<'_, Message> {
+        let Some(projection) = overview::Projection::new(&self.monitors, area) else {
+            return space().into();
+        };
+        let mut layers = stack![space().width(Length::Fill).height(Length::Fill)];
+        for monitor in &self.monitors {
+            let rect = projection.rect(monitor.rect());
+            let active = page.monitor == Some(monitor.id);
+            let outline = container(text(&monitor.name).size(12))
+                .padding(6)
+                .width(rect.width)
+                .height(rect.height)
+                .style(move |theme: &iced::Theme| {
+                    let palette = theme.extended_palette();
+                    let background = if active {
+                        palette.background.weak.color
+                    } else {
+                        palette.background.base.color
+                    };
+                    container::Style::default().background(background).border(Border {
+                        color: palette.background.strong.color,
+                        width: 1.0,
+                        radius: 4.0.into(),
+                    })
+                });
+            layers = layers.push(pin(outline).position(Point::new(rect.x, rect.y)));
+        }
+
+        // Bottom-most first, so windows Hyprland draws on top end up on top.
+        let mut windows: Vec<&WindowEntry> = self
+            .tiles()
+            .into_iter()
+            .filter(|window| page.contains(window.client.as_ref()))
+            .collect();
+        windows.sort_by_key(|window| {
+            let client = window.client.as_ref();
+            let floating = client.is_some_and(|client| client.floating || client.pinned);
+            let recency = client.and_then(|client| client.focus_history_id);
+            (floating, std::cmp::Reverse(recency))
+        });
+        for window in windows {
+            let Some(client) = &window.client else { continue };
+            let rect = projection.rect(client.rect());
+            let size =
+                Size::new(rect.width.max(MIN_OVERVIEW_TILE), rect.height.max(MIN_OVERVIEW_TILE));
+            let tile = self.view_overview_tile(window, size);
+            layers = layers.push(pin(tile).position(Point::new(rect.x, rect.y)));
+        }
+        layers.into()
+    }
+
+    /// A window at its projected size, with its title in a tooltip.
+    fn view_overview_tile<'a>(
+        &'a self,
+        window: &'a WindowEntry,
+        size: Size,
+    ) -> Element<'a, Message> {
+        let picture = match &window.capture {
+            CaptureState::Ready(thumbnail) => {
+                // The grid thumbnail would be stretched; the zoom copy, if any, is sharper.
+                let physical_width = size.width * self.scale_factor.unwrap_or(1.0);
+                let thumbnail = if physical_width > thumbnail.width as f32 {
+                    thumbnail.zoomed()
+                } else {
+                    thumbnail
+                };
+                view_thumbnail(thumbnail, size, false)
+            }
+            _ => self.view_placeholder(window, size),
+        };
+        tooltip(
+            self.view_tile(window, picture, 0),
+            container(text(display_title(window)).size(12))
+                .padding(6)
+                .style(container::rounded_box),
+            tooltip::Position::Bottom,
+        )
+        .into()
+    }
+
+    /// Overview pages for the windows that get tiles.
+    fn overview_pages(&self) -> Vec<overview::Page> {
+        overview::pages(
+            self.windows
+                .iter()
+                .filter(|window| self.tile_of(window) == window.handle_lo)
+                .map(|window| window.client.as_ref()),
+        )
+    }
+
+    /// Windows that get a tile of their own, in display order.
+    fn tiles(&self) -> Vec<&WindowEntry> {
+        let (mut recent, rest) = self.tile_sections();
+        recent.extend(rest);
+        recent
+    }
+
+    /// The Recent row's tiles and the other tiles, in display order.
+    fn tile_sections(&self) -> (Vec<&WindowEntry>, Vec<&WindowEntry>) {
+        let entries = |indices: &[usize]| indices.iter().map(|&i| &self.windows[i]).collect();
+        (entries(&self.sections.0), entries(&self.sections.1))
+    }
+
+    /// Splits the tiles into the Recent row (pinned apps, then the most frecently shared
+    /// windows) and everything else, in sort order. Scoring every window is too slow for each
+    /// frame, so this runs when the windows, their order, the history or the layout change.
+    fn update_sections(&mut self) {
+        let tiles = (0..self.windows.len())
+            .filter(|&i| self.tile_of(&self.windows[i]) == self.windows[i].handle_lo);
+        if self.ui.layout == Layout::Overview {
+            // Page by page, in reading order, so keyboard focus walks the screen.
+            let pages = self.overview_pages();
+            let mut tiles: Vec<usize> = tiles.collect();
+            tiles.sort_by_key(|&i| {
+                let client = self.windows[i].client.as_ref();
+                let page = pages.iter().position(|page| page.contains(client));
+                (page, client.map(|client| (client.at[1], client.at[0])))
+            });
+            self.sections = (Vec::new(), tiles);
+            return;
+        }
+        let mut recent: Vec<(bool, f64, usize)> = Vec::new();
+        let mut rest = Vec::new();
+        for i in tiles {
+            let window = &self.windows[i];
+            let pinned = self.history.is_pinned(&window.class);
+            let score = if config::get().share_history {
+                self.history
+                    .score(&window.class, &window.title, self.launched_at)
             } else {
-                window.title.as_str()
+                0.0
             };
-
-            let subtitle = if window.class.is_empty() {
-                ""
+            if pinned || score >= MIN_RECENT_SCORE {
+                recent.push((pinned, score, i));
             } else {
-                window.class.as_str()
+                rest.push(i);
+            }
+        }
+        // Stable, so equally ranked windows stay in sort order.
+        recent.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.total_cmp(&a.1)));
+        let limit = recent
+            .iter()
+            .filter(|(pinned, ..)| *pinned)
+            .count()
+            .max(RECENT_LIMIT)
+            .min(recent.len());
+        // Windows that didn't make the cut rejoin the rest in sort order, which is index order.
+        if recent.len() > limit {
+            rest.extend(recent.split_off(limit).into_iter().map(|(.., i)| i));
+            rest.sort_unstable();
+        }
+        self.sections = (recent.into_iter().map(|(.., i)| i).collect(), rest);
+    }
+
+    /// The tile a window is drawn in. Children are drawn inside their top-most listed
+    /// ancestor's tile, or dropped when the config hides them; a child whose parent is not in
+    /// the portal list is a regular tile. Compact tiles have no room for children, and the
+    /// overview draws every window where it is, so there every shown child gets a tile of
+    /// its own.
+    fn tile_of(&self, window: &WindowEntry) -> u32 {
+        if matches!(self.ui.layout, Layout::Compact | Layout::Overview)
+            && !config::get().hide_child_windows
+        {
+            window.handle_lo
+        } else {
+            self.root_of(window)
+        }
+    }
+
+    fn root_of(&self, window: &WindowEntry) -> u32 {
+        let mut current = window;
+        // Bounded walk so a parent cycle reported by the compositor can't hang the UI.
+        for _ in 0..self.windows.len() {
+            let Some(parent) = current
+                .parent
+                .and_then(|parent| self.windows.iter().find(|w| w.handle_lo == parent))
+            else {
+                break;
             };
+            current = parent;
+        }
+        current.handle_lo
+    }
 
-            let card = column![
-                thumb,
-                text(title).size(16),
-                text(subtitle).size(12)
-            ]
-                .width(Length::Fixed(220.0))
-                .spacing(8)
-                .align_x(Alignment::Center);
+    fn view_grid_tile<'a>(&'a self, window: &'a WindowEntry) -> Element<'a, Message> {
+        let bounds = self.thumbnail_bounds();
+        let app = self.apps.get(&window.class);
+        let subtitle = match app {
+            Some(app) => app.name.as_str(),
+            None => window.class.as_str(),
+        };
+        let mut subtitle_row = row![].spacing(4).align_y(Alignment::Center);
+        if self.history.is_pinned(&window.class) {
+            subtitle_row = subtitle_row.push(text("★").size(12));
+        }
+        if let Some(icon) = app.and_then(|app| app.icon.as_ref()) {
+            subtitle_row = subtitle_row.push(view_icon(icon, 16.0));
+        }
+
+        let mut card = column![
+            self.view_picture(window, bounds),
+            text(display_title(window)).size(16),
+            subtitle_row.push(text(subtitle).size(12))
+        ]
+            .width(Length::Fixed(bounds.width))
+            .spacing(8)
+            .align_x(Alignment::Center);
+        if let Some(children) = self.view_children(window) {
+            card = card.push(children);
+        }
+
+        self.view_tile(window, card.into(), 8)
+    }
 
-            let button = button(card)
-                .on_press(Message::Select(window.handle_lo))
-                .padding(8);
+    /// One line per window: thumbnail, title, class and workspace, with children below.
+    fn view_list_row<'a>(&'a self, window: &'a WindowEntry) -> Element<'a, Message> {
+        let bounds = self.thumbnail_bounds();
+        let app = self.apps.get(&window.class);
+        let icon: Element<_> = match app.and_then(|app| app.icon.as_ref()) {
+            Some(icon) => view_icon(icon, 20.0),
+            None => iced::widget::space().width(20).into(),
+        };
+        let pin = self.history.is_pinned(&window.class).then(|| text("★").size(12));
+        let line = row![
+            self.view_picture(window, bounds),
+            icon,
+            row![text(display_title(window)).size(14)]
+                .push(pin)
+                .spacing(4)
+                .align_y(Alignment::Center)
+                .width(Length::Fill),
+            text(app.map_or(window.class.as_str(), |app| app.name.as_str())).size(12),
+            text(window.client.as_ref().map_or("", |client| client.workspace.name.as_str()))
+                .size(12),
+        ]
+        .spacing(12)
+        .align_y(Alignment::Center);
 
-            tiles = tiles.push(button);
+        let mut content = column![line].spacing(4).width(Length::Fill);
+        if let Some(children) = self.view_children(window) {
+            content = content.push(container(children).padding(iced::padding::left(bounds.width + 12.0)));
         }
+        self.view_tile(window, content.into(), 4)
+    }
 
-        let content = scrollable(tiles.wrap())
-            .width(Length::Fill)
-            .height(Length::Fill);
+    /// Just the picture with the app icon in its corner, and the title in a tooltip.
+    fn view_compact_tile<'a>(&'a self, window: &'a WindowEntry) -> Element<'a, Message> {
+        let bounds = self.thumbnail_bounds();
+        let corner = |content: Element<'a, Message>| {
+            container(content)
+                .width(Length::Fixed(bounds.width))
+                .height(Length::Fixed(bounds.height))
+        };
+        let mut picture = stack![self.view_picture(window, bounds)];
+        // The placeholder already shows the icon large.
+        if let Some(icon) = self.apps.get(&window.class).and_then(|app| app.icon.as_ref())
+            && matches!(window.capture, CaptureState::Ready(_))
+        {
+            picture = picture.push(
+                corner(view_icon(icon, 20.0))
+                    .align_left(Length::Fixed(bounds.width))
+                    .align_bottom(Length::Fixed(bounds.height)),
+            );
+        }
+        if self.history.is_pinned(&window.class) {
+            picture = picture.push(
+                corner(text("★").size(14).into())
+                    .align_right(Length::Fixed(bounds.width))
+                    .align_top(Length::Fixed(bounds.height)),
+            );
+        }
+        let tile = self.view_tile(window, picture.into(), 4);
+        tooltip(
+            tile,
+            container(text(display_title(window)).size(12))
+                .padding(6)
+                .style(container::rounded_box),
+            tooltip::Position::Bottom,
+        )
+        .into()
+    }
+
+    fn view_picture<'a>(&'a self, window: &'a WindowEntry, bounds: Size) -> Element<'a, Message> {
+        match &window.capture {
+            CaptureState::Ready(thumbnail) => {
+                let style = config::get().style_for(&window.class, &window.title);
+                view_thumbnail(thumbnail, bounds, style.contrast_frame)
+            }
+            _ => self.view_placeholder(window, bounds),
+        }
+    }
+
+    /// Selectable titles of the children drawn inside a tile, if there are any to show.
+    fn view_children<'a>(&'a self, window: &'a WindowEntry) -> Option<Element<'a, Message>> {
+        if config::get().hide_child_windows || self.children_of(window).next().is_none() {
+            return None;
+        }
+        let mut children = column![].spacing(4).width(Length::Fill);
+        for child in self.children_of(window) {
+            let child_button = button(text(display_title(child)).size(12))
+                .on_press(Message::Select(child.handle_lo))
+                .style(button::secondary)
+                .padding([2, 6])
+                .width(Length::Fill);
+            children = children.push(child_button);
+        }
+        Some(children.into())
+    }
+
+    /// Wraps a tile's content in the selectable, hoverable, visibility-reporting button.
+    fn view_tile<'a>(
+        &'a self,
+        window: &'a WindowEntry,
+        content: Element<'a, Message>,
+        padding: u16,
+    ) -> Element<'a, Message> {
+        let focused = self.focused == Some(window.handle_lo);
+        let tile = button(content)
+            .on_press(Message::Select(window.handle_lo))
+            .padding(padding)
+            .style(move |theme: &iced::Theme, status| {
+                let style = button::primary(theme, status);
+                if focused {
+                    button::Style {
+                        border: Border {
+                            color: theme.extended_palette().background.base.text,
+                            width: 2.0,
+                            ..style.border
+                        },
+                        ..style
+                    }
+                } else {
+                    style
+                }
+            });
+        let tile = mouse_area(tile)
+            .on_enter(Message::TileHovered(window.handle_lo))
+            .on_exit(Message::TileUnhovered(window.handle_lo))
+            .on_right_press(Message::TogglePin(window.class.clone()))
+            .on_middle_press(Message::JumpTo(window.handle_lo));
+
+        // Reports a tile about one row before it scrolls in, so its capture is usually done
+        // by the time it is seen.
+        let tile = sensor(tile)
+            .key(window.handle_lo)
+            .anticipate(self.thumbnail_bounds().height)
+            .on_show(move |_| Message::TileShown(window.handle_lo))
+            .on_hide(Message::TileHidden(window.handle_lo));
+        container(tile).id(tile_id(window.handle_lo)).into()
+    }
+
+    /// Larger preview of the zoomed tile with its full title and metadata, or a hint while
+    /// nothing has been hovered or focused yet.
+    fn view_zoom_pane(&self) -> Element<'_, Message> {
+        let bounds = Size::new(ZOOM_WIDTH, ZOOM_HEIGHT);
+        let window = self
+            .zoomed
+            .and_then(|handle_lo| self.windows.iter().find(|w| w.handle_lo == handle_lo));
+        let content: Element<_> = match window {
+            Some(window) => {
+                let picture = match &window.capture {
+                    CaptureState::Ready(thumbnail) => {
+                        let style = config::get().style_for(&window.class, &window.title);
+                        view_thumbnail(thumbnail.zoomed(), bounds, style.contrast_frame)
+                    }
+                    _ => self.view_placeholder(window, bounds),
+                };
+                let class = match self.apps.get(&window.class) {
+                    Some(app) => format!("{} ({})", app.name, window.class),
+                    None => window.class.clone(),
+                };
+                let mut details = column![
+                    picture,
+                    text(display_title(window)).size(16),
+                    text(class).size(12)
+                ]
+                .spacing(8);
+                let children = self
+                    .windows
+                    .iter()
+                    .filter(|w| w.parent == Some(window.handle_lo))
+                    .count();
+                for line in window_details(window, children) {
+                    details = details.push(text(line).size(12));
+                }
+                details.into()
+            }
+            None => container(text("Hover a window, or press Tab, to see it larger").size(14))
+                .center_x(Length::Fill)
+                .center_y(Length::Fixed(bounds.height))
+                .into(),
+        };
 
         container(content)
-            .width(Length::Fill)
+            .width(Length::Fixed(ZOOM_WIDTH + 24.0))
             .height(Length::Fill)
-            .padding(16)
+            .padding(12)
+            .style(container::rounded_box)
             .into()
     }
 
+    fn children_of<'a>(&'a self, window: &'a WindowEntry) -> impl Iterator<Item = &'a WindowEntry> {
+        self.windows
+            .iter()
+            .filter(|w| w.handle_lo != window.handle_lo && self.tile_of(w) == window.handle_lo)
+    }
+
+    /// Stands in for a missing thumbnail: the app icon, if known, above the capture state.
+    fn view_placeholder(&self, window: &WindowEntry, bounds: Size) -> Element<'_, Message> {
+        let (label, detail) = match &window.capture {
+            // Evicted tiles are off screen, and go back to pending as soon as they return.
+            CaptureState::Pending | CaptureState::Evicted => {
+                let spinner = SPINNER[self.spinner_frame];
+                (format!("{spinner} Capturing…"), None)
+            }
+            CaptureState::Failed(error) => (capture_error_label(error).to_string(), Some(error.to_string())),
+            CaptureState::NoMatch => (
+                "No preview available".to_string(),
+                Some("No compositor toplevel matched this window's class and title".to_string()),
+            ),
+            CaptureState::Hidden => (
+                "Preview hidden".to_string(),
+                Some("Hidden by a rule in config.toml".to_string()),
+            ),
+            CaptureState::Ready(_) => (String::new(), None),
+        };
+
+        let mut content = column![].spacing(6).align_x(Alignment::Center);
+        if let Some(icon) = self.apps.get(&window.class).and_then(|app| app.icon.as_ref()) {
+            content = content.push(view_icon(icon, (bounds.height * 0.4).min(48.0)));
+        }
+        let placeholder = container(content.push(text(label).size(14)))
+            .width(Length::Fixed(bounds.width))
+            .height(Length::Fixed(bounds.height))
+            .center_x(Length::Fixed(bounds.width))
+            .center_y(Length::Fixed(bounds.height));
+
+        match detail {
+            Some(detail) => tooltip(
+                placeholder,
+                container(text(detail).size(12))
+                    .padding(6)
+                    .style(container::rounded_box),
+                tooltip::Position::Bottom,
+            )
+            .into(),
+            None => placeholder.into(),
+        }
+    }
+
     fn subscription(&self) -> Subscription<Message> {
-        Subscription::batch(vec![
+        let mut subscriptions = vec![
             wayland::subscription().map(Message::Wayland),
             iced::event::listen().map(Message::UiEvent),
+            iced::event::listen_with(|event, _status, _window| match event {
+                Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                    Some(Message::WheelScrolled(delta))
+                }
+                _ => None,
+            }),
             iced::window::close_requests().map(|_| Message::CloseRequested),
-        ])
+            iced::window::open_events().map(Message::WindowOpened),
+        ];
+        if self
+            .windows
+            .iter()
+            .any(|w| matches!(w.capture, CaptureState::Pending))
+        {
+            subscriptions.push(iced::time::every(SPINNER_FRAME).map(|_| Message::SpinnerTick));
+        }
+        if self.zoom_target.is_some() || self.resize_pending.is_some() {
+            subscriptions.push(iced::time::every(TIMER_RESOLUTION).map(Message::Tick));
+        }
+        Subscription::batch(subscriptions)
     }
 }
 
-impl Default for App {
-    fn default() -> Self {
-        Self {
-            windows: Vec::new(),
-            allow_token: false,
-        }
+fn view_icon(icon: &Icon, size: f32) -> Element<'_, Message> {
+    match icon {
+        Icon::Raster(handle) => image(handle.clone()).width(size).height(size).into(),
+        Icon::Vector(handle) => svg(handle.clone()).width(size).height(size).into(),
     }
 }
 
-fn parse_window_list() -> Vec<WindowEntry> {
-    let raw = std::env::var("XDPH_WINDOW_SHARING_LIST").unwrap_or_default();
-    let mut entries = Vec::new();
-    let mut input = raw.as_str();
-    let mut counts: std::collections::HashMap<(String, String), usize> = std::collections::HashMap::new();
-    let mut temp = Vec::new();
-
-    while let Some(hc) = input.find("[HC>]") {
-        let (handle_str, rest) = input.split_at(hc);
-        let handle_lo = handle_str.trim().parse::<u32>().unwrap_or(0);
-        let rest = &rest["[HC>]".len()..];
-
-        let Some(ht_pos) = rest.find("[HT>]") else { break };
-        let (class, rest) = rest.split_at(ht_pos);
-        let rest = &rest["[HT>]".len()..];
-
-        let Some(he_pos) = rest.find("[HE>]") else { break };
-        let (title, rest) = rest.split_at(he_pos);
-        let rest = &rest["[HE>]".len()..];
-
-        let Some(ha_pos) = rest.find("[HA>]") else { break };
-        let (mapped, rest) = rest.split_at(ha_pos);
-        let rest = &rest["[HA>]".len()..];
-
-        let mapped_id = mapped.trim().parse::<u64>().unwrap_or(0);
-
-        let class = class.to_string();
-        let title = title.to_string();
-        *counts.entry((class.clone(), title.clone())).or_insert(0) += 1;
-        temp.push((handle_lo, class, title, mapped_id));
-
-        input = rest;
-    }
-
-    let mut seen: std::collections::HashMap<(String, String), usize> = std::collections::HashMap::new();
-    for (handle_lo, class, title, mapped_id) in temp {
-        let group_key = (class.clone(), title.clone());
-        let group_size = *counts.get(&group_key).unwrap_or(&1);
-        let entry = seen.entry(group_key).or_insert(0);
-        let group_index = *entry;
-        *entry += 1;
-
-        entries.push(WindowEntry {
-            handle_lo,
-            class,
-            title,
-            mapped_id,
-            group_index,
-            group_size,
-            thumbnail: None,
-        });
+/// Draws the thumbnail letterboxed into `bounds`, over the configured backdrop, optionally
+/// inside a thin contrast frame.
+fn view_thumbnail(
+    thumbnail: &WindowThumbnail,
+    bounds: Size,
+    contrast_frame: bool,
+) -> Element<'_, Message> {
+    // Leave room for the frame's padding (which the 1px border sits in) on each side.
+    let inset = if contrast_frame { 4.0 } else { 0.0 };
+    let fitted = thumbnail.fitted_size(Size::new(bounds.width - inset, bounds.height - inset));
+    let picture = image(thumbnail.handle.clone())
+        .width(Length::Fixed(fitted.width))
+        .height(Length::Fixed(fitted.height));
+
+    let content: Element<_> = match config::get().thumbnail_backdrop {
+        Backdrop::None => picture.into(),
+        Backdrop::Checkerboard => stack![
+            canvas(widget::Checkerboard)
+                .width(Length::Fixed(fitted.width))
+                .height(Length::Fixed(fitted.height)),
+            picture
+        ]
+        .into(),
+        Backdrop::Theme => container(picture)
+            .style(|theme: &iced::Theme| {
+                container::Style::default().background(theme.palette().background)
+            })
+            .into(),
+    };
+
+    let content: Element<_> = if contrast_frame {
+        container(content)
+            .padding(2)
+            .style(|theme: &iced::Theme| {
+                let palette = theme.extended_palette();
+                container::Style::default().border(Border {
+                    color: widget::mix(palette.background.base.color, palette.background.base.text, 0.4),
+                    width: 1.0,
+                    radius: 2.0.into(),
+                })
+            })
+            .into()
+    } else {
+        content
+    };
+
+    container(content)
+        .width(Length::Fixed(bounds.width))
+        .height(Length::Fixed(bounds.height))
+        .center_x(Length::Fixed(bounds.width))
+        .center_y(Length::Fixed(bounds.height))
+        .into()
+}
+
+/// Default picture areas per layout, before the user's tile zoom; all share the grid's aspect.
+const TILE_WIDTH: f32 = 220.0;
+const TILE_HEIGHT: f32 = 140.0;
+const LIST_THUMBNAIL_WIDTH: f32 = 110.0;
+const LIST_THUMBNAIL_HEIGHT: f32 = 70.0;
+const COMPACT_WIDTH: f32 = 88.0;
+const COMPACT_HEIGHT: f32 = 56.0;
+
+/// The Recent row holds every pinned window, plus the best-scoring shared ones up to this
+/// many in total.
+const RECENT_LIMIT: usize = 6;
+/// What a single share scores after about a month, with weights halving every week.
+const MIN_RECENT_SCORE: f64 = 0.05;
+
+/// Smallest side of an overview tile, so tiny windows stay clickable.
+const MIN_OVERVIEW_TILE: f32 = 24.0;
+
+/// Tile zoom step, in percent.
+const TILE_ZOOM_STEP: u32 = 10;
+/// Touchpad scroll distance per zoom step.
+const WHEEL_PIXELS_PER_STEP: f32 = 40.0;
+/// How long the tile size must stay put before thumbnails are regenerated and it is saved.
+const RESIZE_SETTLE: Duration = Duration::from_millis(400);
+
+/// Picture area of the zoom pane, and how long a tile must stay hovered or focused to fill it.
+const ZOOM_WIDTH: f32 = 400.0;
+const ZOOM_HEIGHT: f32 = 250.0;
+const ZOOM_DELAY: Duration = Duration::from_millis(300);
+
+/// The scrollable holding the tiles, so keyboard focus can scroll it.
+const GRID_SCROLLABLE: &str = "grid";
+
+/// Widget id of a tile, for scrolling it into view.
+fn tile_id(handle_lo: u32) -> iced::widget::Id {
+    iced::widget::Id::from(format!("tile-{handle_lo}"))
+}
+
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_FRAME: Duration = Duration::from_millis(80);
+/// How late the zoom and resize delays may fire.
+const TIMER_RESOLUTION: Duration = Duration::from_millis(50);
+
+fn capture_error_label(error: &CaptureError) -> &'static str {
+    match error {
+        CaptureError::Failed => "Capture failed",
+        CaptureError::UnsupportedFormat(_) => "Unsupported pixel format",
+        CaptureError::DmabufOnly => "GPU buffers only",
+        CaptureError::InvalidFrame(_) => "Invalid frame",
+        CaptureError::Shm(_) => "Out of shared memory",
+        CaptureError::Protocol(_) => "Compositor error",
+        CaptureError::TimedOut { .. } => "Timed out",
+    }
+}
+
+fn display_title(window: &WindowEntry) -> &str {
+    if window.title.is_empty() {
+        "<untitled>"
+    } else {
+        window.title.as_str()
     }
+}
 
-    entries
+/// Metadata lines for the zoom pane, below the title and class.
+fn window_details(window: &WindowEntry, children: usize) -> Vec<String> {
+    let mut details = Vec::new();
+    if window.group_size > 1 {
+        details.push(format!(
+            "Window {} of {} with this class and title",
+            window.group_index + 1,
+            window.group_size
+        ));
+    }
+    match children {
+        0 => {}
+        1 => details.push("1 child window".to_string()),
+        n => details.push(format!("{n} child windows")),
+    }
+    match &window.capture {
+        CaptureState::Ready(thumbnail) => {
+            let shown = thumbnail.zoomed();
+            details.push(format!("Preview {}×{} px", shown.width, shown.height));
+        }
+        CaptureState::Failed(error) => details.push(error.to_string()),
+        _ => {}
+    }
+    details.push(format!("Portal handle {:#x}", window.handle_lo));
+    details
+}
+
+fn parse_window_list() -> Vec<WindowEntry> {
+    // Only used for display and sorting, so the picker works without Hyprland's IPC (e.g.
+    // when testing).
+    let clients = hyprland::clients().unwrap_or_else(|error| {
+        wayland::debug_log(&format!("hyprland: can't list clients: {error}"));
+        Vec::new()
+    });
+    let clients = hyprland::for_portal_windows(portal::windows(), clients);
+    portal::windows()
+        .iter()
+        .zip(clients)
+        .enumerate()
+        .map(|(portal_index, (window, client))| {
+            let capture = if config::get().hides_preview(&window.class, &window.title) {
+                CaptureState::Hidden
+            } else {
+                CaptureState::Pending
+            };
+            WindowEntry {
+                handle_lo: window.handle_lo,
+                class: window.class.clone(),
+                title: window.title.clone(),
+                mapped_id: window.mapped_id,
+                group_index: window.group_index,
+                group_size: window.group_size,
+                capture,
+                parent: None,
+                portal_index,
+                client,
+                activation: None,
+                last_visible: None,
+            }
+        })
+        .collect()
 }
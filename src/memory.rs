@@ -0,0 +1,60 @@
+use crate::config;
+use crate::wayland::debug_log;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Pixel memory held by the picker, shared by the Wayland thread (shm pool) and the UI
+/// (decoded thumbnails). Both count towards the budget, but only thumbnails are evicted to
+/// meet it; the pool is bounded by `max-concurrent-captures` instead.
+pub struct MemoryStats {
+    shm_pool: AtomicUsize,
+    thumbnails: AtomicUsize,
+}
+
+pub fn stats() -> &'static MemoryStats {
+    static STATS: MemoryStats = MemoryStats {
+        shm_pool: AtomicUsize::new(0),
+        thumbnails: AtomicUsize::new(0),
+    };
+    &STATS
+}
+
+/// The configured budget in bytes.
+pub fn budget() -> usize {
+    config::get().memory_budget_mb.saturating_mul(1024 * 1024)
+}
+
+impl MemoryStats {
+    pub fn set_shm_pool(&self, bytes: usize) {
+        if self.shm_pool.swap(bytes, Ordering::Relaxed) != bytes {
+            self.report();
+        }
+    }
+
+    pub fn add_thumbnail(&self, bytes: usize) {
+        self.thumbnails.fetch_add(bytes, Ordering::Relaxed);
+        self.report();
+    }
+
+    pub fn remove_thumbnail(&self, bytes: usize) {
+        self.thumbnails.fetch_sub(bytes, Ordering::Relaxed);
+        self.report();
+    }
+
+    pub fn total(&self) -> usize {
+        self.shm_pool.load(Ordering::Relaxed) + self.thumbnails.load(Ordering::Relaxed)
+    }
+
+    pub fn over_budget(&self) -> bool {
+        self.total() > budget()
+    }
+
+    fn report(&self) {
+        let mib = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+        debug_log(&format!(
+            "memory: shm pool {:.1} MiB, thumbnails {:.1} MiB, budget {:.0} MiB",
+            mib(self.shm_pool.load(Ordering::Relaxed)),
+            mib(self.thumbnails.load(Ordering::Relaxed)),
+            mib(budget()),
+        ));
+    }
+}
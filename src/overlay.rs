@@ -0,0 +1,247 @@
+use crate::config::APP_DIR;
+use iced::Rectangle;
+use smithay_client_toolkit::compositor::{CompositorHandler, CompositorState};
+use smithay_client_toolkit::output::{OutputHandler, OutputState};
+use smithay_client_toolkit::registry::RegistryState;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::{
+    Layer, LayerShell, LayerShellHandler, LayerSurface, LayerSurfaceConfigure,
+};
+use smithay_client_toolkit::shm::slot::SlotPool;
+use smithay_client_toolkit::shm::{Shm, ShmHandler};
+use smithay_client_toolkit::{delegate_compositor, delegate_layer, delegate_output, delegate_shm};
+use wayland_client::globals::{GlobalList, GlobalListContents, registry_queue_init};
+use wayland_client::protocol::{wl_output, wl_registry, wl_shm, wl_surface};
+use wayland_client::{Connection, Dispatch, EventQueue, QueueHandle};
+
+/// Premultiplied ARGB outline of the window being pointed at, on screen or through a tile.
+pub const HIGHLIGHT_BORDER: u32 = 0xff5e_a9ff;
+
+/// What differs between the on-screen overlays: which layer surfaces they show and what is
+/// drawn on them.
+pub trait Content: Sized + 'static {
+    /// The compositor gave `layer` its size, so it can be drawn.
+    fn configure(&mut self, painter: &mut Painter, layer: &LayerSurface, size: (u32, u32));
+
+    /// The compositor closed `layer`, e.g. because its output went away.
+    fn closed(&mut self, layer: &LayerSurface);
+}
+
+/// Wayland state of an on-screen overlay: layer-shell surfaces on a connection of its own,
+/// drawn from shared memory. Whatever the overlay shows lives in `content`.
+pub struct Overlay<T> {
+    pub registry_state: RegistryState,
+    pub output_state: OutputState,
+    pub compositor: CompositorState,
+    layer_shell: LayerShell,
+    pub qh: QueueHandle<Self>,
+    pub painter: Painter,
+    pub content: T,
+}
+
+pub type Connected<T> = (Connection, EventQueue<Overlay<T>>, Overlay<T>);
+
+/// Connects to the compositor, binds what an overlay needs and sets up its `content` from
+/// the globals. Output names and positions arrive with the roundtrip this ends with, so
+/// outputs can be matched to monitors right away.
+pub fn connect<T: Content>(
+    content: impl FnOnce(&GlobalList, &QueueHandle<Overlay<T>>) -> T,
+) -> Result<Connected<T>, Box<dyn std::error::Error>>
+where
+    Overlay<T>: Dispatch<wl_registry::WlRegistry, GlobalListContents>,
+{
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<Overlay<T>>(&conn)?;
+    let qh = queue.handle();
+    let mut overlay = Overlay {
+        registry_state: RegistryState::new(&globals),
+        output_state: OutputState::new(&globals, &qh),
+        compositor: CompositorState::bind(&globals, &qh)?,
+        layer_shell: LayerShell::bind(&globals, &qh)?,
+        painter: Painter {
+            shm: Shm::bind(&globals, &qh)?,
+            pool: None,
+        },
+        content: content(&globals, &qh),
+        qh,
+    };
+    queue.roundtrip(&mut overlay)?;
+    Ok((conn, queue, overlay))
+}
+
+impl<T: Content> Overlay<T> {
+    /// A surface on the overlay layer of `output`, positioned against the output's edges rather
+    /// than the space panels leave free. Nothing shows until it is set up and committed.
+    pub fn create_layer(&self, output: &wl_output::WlOutput) -> LayerSurface {
+        let surface = self.compositor.create_surface(&self.qh);
+        let layer = self.layer_shell.create_layer_surface(
+            &self.qh,
+            surface,
+            Layer::Overlay,
+            Some(APP_DIR),
+            Some(output),
+        );
+        layer.set_exclusive_zone(-1);
+        layer
+    }
+}
+
+/// Hands out ARGB buffers for the overlay's surfaces from one shared-memory pool.
+pub struct Painter {
+    shm: Shm,
+    pool: Option<SlotPool>,
+}
+
+impl Painter {
+    pub fn wl_shm(&self) -> &wl_shm::WlShm {
+        self.shm.wl_shm()
+    }
+
+    /// Paints a `width`×`height` buffer and shows it on `layer`. Buffers are at scale 1;
+    /// there is nothing on them that needs to be sharp.
+    pub fn draw(
+        &mut self,
+        layer: &LayerSurface,
+        (width, height): (u32, u32),
+        paint: impl FnOnce(&mut Canvas),
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let stride = width as i32 * 4;
+        let pool = match &mut self.pool {
+            Some(pool) => pool,
+            pool => pool.insert(SlotPool::new(stride as usize * height as usize, &self.shm)?),
+        };
+        let (buffer, pixels) = pool.create_buffer(
+            width as i32,
+            height as i32,
+            stride,
+            wl_shm::Format::Argb8888,
+        )?;
+        paint(&mut Canvas {
+            pixels,
+            width: width as i32,
+            height: height as i32,
+        });
+        let surface = layer.wl_surface();
+        surface.damage_buffer(0, 0, width as i32, height as i32);
+        buffer.attach_to(surface)?;
+        layer.commit();
+        Ok(())
+    }
+}
+
+/// The pixels of one buffer, premultiplied ARGB.
+pub struct Canvas<'a> {
+    pixels: &'a mut [u8],
+    width: i32,
+    height: i32,
+}
+
+impl Canvas<'_> {
+    pub fn fill(&mut self, color: u32) {
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color.to_le_bytes());
+        }
+    }
+
+    /// Fills `rect` with `fill`, inside a `border_width` wide `border` along its edges. Parts
+    /// off the canvas are skipped.
+    pub fn frame(&mut self, rect: Rectangle<i32>, border_width: i32, border: u32, fill: u32) {
+        let (left, top) = (rect.x, rect.y);
+        let (right, bottom) = (left + rect.width, top + rect.height);
+        for y in top.max(0)..bottom.min(self.height) {
+            for x in left.max(0)..right.min(self.width) {
+                let edge = x - left < border_width
+                    || right - x <= border_width
+                    || y - top < border_width
+                    || bottom - y <= border_width;
+                let color = if edge { border } else { fill };
+                let offset = (y * self.width + x) as usize * 4;
+                self.pixels[offset..offset + 4].copy_from_slice(&color.to_le_bytes());
+            }
+        }
+    }
+}
+
+impl<T: Content> CompositorHandler for Overlay<T> {
+    fn scale_factor_changed(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: i32,
+    ) {
+    }
+
+    fn transform_changed(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: wl_output::Transform,
+    ) {
+    }
+
+    fn frame(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: u32) {}
+
+    fn surface_enter(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: &wl_output::WlOutput,
+    ) {
+    }
+
+    fn surface_leave(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: &wl_output::WlOutput,
+    ) {
+    }
+}
+
+impl<T: Content> OutputHandler for Overlay<T> {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+
+    fn update_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+
+    fn output_destroyed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+}
+
+impl<T: Content> LayerShellHandler for Overlay<T> {
+    fn closed(&mut self, _: &Connection, _: &QueueHandle<Self>, layer: &LayerSurface) {
+        self.content.closed(layer);
+    }
+
+    fn configure(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        layer: &LayerSurface,
+        configure: LayerSurfaceConfigure,
+        _: u32,
+    ) {
+        let (width, height) = configure.new_size;
+        if width > 0 && height > 0 {
+            self.content
+                .configure(&mut self.painter, layer, (width, height));
+        }
+    }
+}
+
+impl<T: Content> ShmHandler for Overlay<T> {
+    fn shm_state(&mut self) -> &mut Shm {
+        &mut self.painter.shm
+    }
+}
+
+delegate_compositor!(@<T: Content> Overlay<T>);
+delegate_output!(@<T: Content> Overlay<T>);
+delegate_shm!(@<T: Content> Overlay<T>);
+delegate_layer!(@<T: Content> Overlay<T>);
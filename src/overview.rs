@@ -0,0 +1,144 @@
+use crate::hyprland::{Client, Monitor};
+use iced::{Point, Rectangle, Size};
+
+/// One page of the overview: a workspace, or the windows Hyprland didn't describe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    /// `None` for the page of windows without IPC data.
+    pub workspace: Option<i64>,
+    pub name: String,
+    /// Monitor the workspace is on.
+    pub monitor: Option<i64>,
+}
+
+impl Page {
+    pub fn contains(&self, client: Option<&Client>) -> bool {
+        client.map(|client| client.workspace.id) == self.workspace
+    }
+}
+
+/// One page per workspace holding any of the windows, numbered workspaces first and special
+/// ones after, then a last page for windows without IPC data, if there are any.
+pub fn pages<'a>(clients: impl IntoIterator<Item = Option<&'a Client>>) -> Vec<Page> {
+    let mut pages: Vec<Page> = Vec::new();
+    let mut unknown = false;
+    for client in clients {
+        let Some(client) = client else {
+            unknown = true;
+            continue;
+        };
+        if pages
+            .iter()
+            .all(|page| page.workspace != Some(client.workspace.id))
+        {
+            pages.push(Page {
+                workspace: Some(client.workspace.id),
+                name: client.workspace.name.clone(),
+                monitor: Some(client.monitor),
+            });
+        }
+    }
+    pages.sort_by_key(|page| page.workspace.map(|id| (id <= 0, id.abs())));
+    if unknown {
+        pages.push(Page {
+            workspace: None,
+            name: "Other".to_string(),
+            monitor: None,
+        });
+    }
+    pages
+}
+
+/// Maps the global layout, in logical pixels, onto an area of the picker so every monitor
+/// fits, centred.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    scale: f32,
+    /// Global layout point drawn at the area's top-left corner.
+    origin: Point,
+}
+
+impl Projection {
+    pub fn new(monitors: &[Monitor], area: Size) -> Option<Self> {
+        let bounds = monitors
+            .iter()
+            .map(Monitor::rect)
+            .reduce(|a, b| a.union(&b))
+            .filter(|bounds| bounds.width > 0.0 && bounds.height > 0.0)?;
+        let scale = (area.width / bounds.width).min(area.height / bounds.height);
+        let margin = Size::new(
+            (area.width / scale - bounds.width) / 2.0,
+            (area.height / scale - bounds.height) / 2.0,
+        );
+        Some(Self {
+            scale,
+            origin: Point::new(bounds.x - margin.width, bounds.y - margin.height),
+        })
+    }
+
+    pub fn rect(&self, rect: Rectangle) -> Rectangle {
+        Rectangle {
+            x: (rect.x - self.origin.x) * self.scale,
+            y: (rect.y - self.origin.y) * self.scale,
+            width: rect.width * self.scale,
+            height: rect.height * self.scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hyprland;
+    use crate::hyprland::fixtures::{self, monitor};
+    use crate::portal;
+
+    fn client(workspace: i64) -> Client {
+        Client {
+            monitor: 1,
+            ..fixtures::client("0x1", workspace)
+        }
+    }
+
+    #[test]
+    fn pages_follow_workspace_numbers() {
+        let clients = [client(3), client(-98), client(1), client(3)];
+        let pages = pages(clients.iter().map(Some).chain([None]));
+        let names: Vec<&str> = pages.iter().map(|page| page.name.as_str()).collect();
+        assert_eq!(names, ["1", "3", "special:magic", "Other"]);
+        assert!(pages[1].contains(Some(&clients[0])));
+        assert!(pages[3].contains(None));
+    }
+
+    #[test]
+    fn pages_portal_windows_by_their_mapped_clients() {
+        // The second window's handle and address agree in their low bits, the first's don't.
+        let windows = portal::parse_window_list(
+            "1834059424[HC>]kitty[HT>]~[HE>]94355175105184[HA>]\
+             3333550864[HC>]firefox[HT>]Example[HE>]94355175109392[HA>]",
+        );
+        let clients = hyprland::for_portal_windows(
+            &windows,
+            vec![
+                fixtures::client("0x55d0c6b1f310", 2),
+                fixtures::client("0x55d0c6b1e2a0", 5),
+            ],
+        );
+        let pages = pages(clients.iter().map(Option::as_ref));
+        let names: Vec<&str> = pages.iter().map(|page| page.name.as_str()).collect();
+        assert_eq!(names, ["2", "5"]);
+        assert!(pages[1].contains(clients[0].as_ref()));
+    }
+
+    #[test]
+    fn projects_all_monitors_into_the_area() {
+        let monitors = [monitor(0, "DP-1", 0, 1), monitor(1, "DP-2", 1920, 2)];
+        let projection = Projection::new(&monitors, Size::new(960.0, 540.0)).unwrap();
+        // 3840×1080 scaled by 1/4 is 960×270, centred vertically.
+        assert_eq!(
+            projection.rect(monitors[1].rect()),
+            Rectangle::new(Point::new(480.0, 135.0), Size::new(480.0, 270.0))
+        );
+        assert_eq!(Projection::new(&[], Size::new(960.0, 540.0)), None);
+    }
+}
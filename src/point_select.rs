@@ -0,0 +1,419 @@
+use crate::hyprland::{self, Client, Monitor};
+use crate::overlay::{self, Content, HIGHLIGHT_BORDER, Overlay, Painter};
+use crate::sort;
+use iced::{Point, Rectangle};
+use smithay_client_toolkit::output::OutputState;
+use smithay_client_toolkit::registry::{ProvidesRegistryState, RegistryState};
+use smithay_client_toolkit::seat::keyboard::{
+    KeyEvent, KeyboardHandler, Keysym, Modifiers, RawModifiers,
+};
+use smithay_client_toolkit::seat::pointer::{
+    CursorIcon, PointerData, PointerEvent, PointerEventKind, PointerHandler, ThemeSpec,
+    ThemedPointer,
+};
+use smithay_client_toolkit::seat::{Capability, SeatHandler, SeatState};
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::{Anchor, KeyboardInteractivity, LayerSurface};
+use smithay_client_toolkit::{
+    delegate_keyboard, delegate_pointer, delegate_registry, delegate_seat, registry_handlers,
+};
+use std::collections::HashMap;
+use wayland_client::protocol::{wl_keyboard, wl_output, wl_pointer, wl_seat, wl_surface};
+use wayland_client::{Connection, QueueHandle};
+
+/// Linux input event codes for the buttons the overlay reacts to.
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+
+/// Premultiplied ARGB. The screen is dimmed slightly so it is obvious the overlay is up.
+const DIM: u32 = 0x3300_0000;
+const HIGHLIGHT_FILL: u32 = 0x4017_2a3f;
+const BORDER_WIDTH: i32 = 3;
+
+/// A window on screen that the pointer can land on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    /// The portal's handle for the window, or `None` if the portal didn't offer it. Those
+    /// still cover what is under them.
+    pub handle_lo: Option<u32>,
+    /// Where the window is in the global layout, in logical pixels.
+    pub rect: Rectangle,
+}
+
+/// Windows on the workspaces that are on screen, top-most first. Open special workspaces
+/// are drawn over the regular ones, and within a workspace the order follows
+/// [`sort::stacking_order`]. `candidates` holds the portal's handles by mapped id.
+pub fn targets(
+    clients: &[Client],
+    monitors: &[Monitor],
+    candidates: &HashMap<u64, u32>,
+) -> Vec<Target> {
+    let mut shown: Vec<&Client> = clients
+        .iter()
+        .filter(|client| {
+            monitors
+                .iter()
+                .any(|monitor| monitor.shows(client.workspace.id))
+        })
+        .collect();
+    shown.sort_by_key(|client| (client.workspace.id > 0, sort::stacking_order(client)));
+    shown
+        .into_iter()
+        .map(|client| Target {
+            handle_lo: client.id().and_then(|id| candidates.get(&id).copied()),
+            rect: client.rect(),
+        })
+        .collect()
+}
+
+/// Index of the top-most window under a point in the global layout.
+pub fn hit(targets: &[Target], point: Point) -> Option<usize> {
+    targets
+        .iter()
+        .position(|target| target.rect.contains(point))
+}
+
+/// Covers every output with a transparent overlay and highlights the window under the
+/// pointer. Returns the `handle_lo` of the window clicked, or `None` once the overlay is
+/// dismissed with Escape or a right click. Blocks until then, so run it off the UI thread.
+pub fn run(candidates: HashMap<u64, u32>) -> Result<Option<u32>, Box<dyn std::error::Error>> {
+    let monitors = hyprland::monitors()?;
+    let targets = targets(&hyprland::clients()?, &monitors, &candidates);
+
+    let (_conn, mut queue, mut overlay) = overlay::connect(|globals, qh| Picker {
+        seat_state: SeatState::new(globals, qh),
+        surfaces: Vec::new(),
+        targets,
+        hovered: None,
+        pointer: None,
+        keyboard: None,
+        result: None,
+    })?;
+
+    let outputs: Vec<wl_output::WlOutput> = overlay.output_state.outputs().collect();
+    for output in outputs {
+        let info = overlay.output_state.info(&output);
+        let monitor = info
+            .as_ref()
+            .and_then(|info| info.name.as_ref())
+            .and_then(|name| monitors.iter().find(|monitor| monitor.name == *name));
+        // Hyprland's layout is what the client positions are in; xdg-output agrees with it,
+        // but only if the compositor sent it.
+        let origin = match (monitor, info.and_then(|info| info.logical_position)) {
+            (Some(monitor), _) => monitor.rect().position(),
+            (None, Some((x, y))) => Point::new(x as f32, y as f32),
+            (None, None) => Point::ORIGIN,
+        };
+        let layer = overlay.create_layer(&output);
+        layer.set_anchor(Anchor::TOP | Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT);
+        layer.set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
+        layer.commit();
+        overlay.content.surfaces.push(OverlaySurface {
+            layer,
+            origin,
+            size: None,
+        });
+    }
+
+    while overlay.content.result.is_none() {
+        queue.blocking_dispatch(&mut overlay)?;
+    }
+    Ok(overlay.content.result.flatten())
+}
+
+struct Picker {
+    seat_state: SeatState,
+    surfaces: Vec<OverlaySurface>,
+    targets: Vec<Target>,
+    /// Index into `targets` of the window under the pointer.
+    hovered: Option<usize>,
+    pointer: Option<ThemedPointer<PointerData>>,
+    keyboard: Option<wl_keyboard::WlKeyboard>,
+    /// Set once the overlay is done: the clicked window, or `None` if dismissed.
+    result: Option<Option<u32>>,
+}
+
+struct OverlaySurface {
+    layer: LayerSurface,
+    /// Where the output's top-left corner is in the global layout.
+    origin: Point,
+    /// Logical size, once the compositor has configured the surface.
+    size: Option<(u32, u32)>,
+}
+
+impl Picker {
+    fn surface_index(&self, surface: &wl_surface::WlSurface) -> Option<usize> {
+        self.surfaces
+            .iter()
+            .position(|overlay| overlay.layer.wl_surface() == surface)
+    }
+
+    fn set_hovered(&mut self, painter: &mut Painter, hovered: Option<usize>) {
+        if self.hovered != hovered {
+            self.hovered = hovered;
+            for index in 0..self.surfaces.len() {
+                self.draw(painter, index);
+            }
+        }
+    }
+
+    /// Paints one output: dimmed, with the hovered window filled and outlined.
+    fn draw(&mut self, painter: &mut Painter, index: usize) {
+        let surface = &self.surfaces[index];
+        let Some(size) = surface.size else {
+            return;
+        };
+        let hovered = self
+            .hovered
+            .map(|hovered| &self.targets[hovered])
+            .filter(|target| target.handle_lo.is_some())
+            .map(|target| Rectangle {
+                x: (target.rect.x - surface.origin.x) as i32,
+                y: (target.rect.y - surface.origin.y) as i32,
+                width: target.rect.width as i32,
+                height: target.rect.height as i32,
+            });
+        let drawn = painter.draw(&surface.layer, size, |canvas| {
+            canvas.fill(DIM);
+            if let Some(rect) = hovered {
+                canvas.frame(rect, BORDER_WIDTH, HIGHLIGHT_BORDER, HIGHLIGHT_FILL);
+            }
+        });
+        if let Err(error) = drawn {
+            eprintln!("Point-and-click error: {error}");
+            self.result = Some(None);
+        }
+    }
+}
+
+impl Content for Picker {
+    fn configure(&mut self, painter: &mut Painter, layer: &LayerSurface, size: (u32, u32)) {
+        let Some(index) = self.surface_index(layer.wl_surface()) else {
+            return;
+        };
+        self.surfaces[index].size = Some(size);
+        self.draw(painter, index);
+    }
+
+    fn closed(&mut self, _: &LayerSurface) {
+        self.result = Some(None);
+    }
+}
+
+impl SeatHandler for Overlay<Picker> {
+    fn seat_state(&mut self) -> &mut SeatState {
+        &mut self.content.seat_state
+    }
+
+    fn new_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_seat::WlSeat) {}
+
+    fn new_capability(
+        &mut self,
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+        seat: wl_seat::WlSeat,
+        capability: Capability,
+    ) {
+        let picker = &mut self.content;
+        if capability == Capability::Keyboard && picker.keyboard.is_none() {
+            picker.keyboard = picker.seat_state.get_keyboard(qh, &seat, None).ok();
+        }
+        if capability == Capability::Pointer && picker.pointer.is_none() {
+            let cursor_surface = self.compositor.create_surface(qh);
+            picker.pointer = picker
+                .seat_state
+                .get_pointer_with_theme(
+                    qh,
+                    &seat,
+                    self.painter.wl_shm(),
+                    cursor_surface,
+                    ThemeSpec::default(),
+                )
+                .ok();
+        }
+    }
+
+    fn remove_capability(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: wl_seat::WlSeat,
+        capability: Capability,
+    ) {
+        if capability == Capability::Keyboard
+            && let Some(keyboard) = self.content.keyboard.take()
+        {
+            keyboard.release();
+        }
+        if capability == Capability::Pointer
+            && let Some(pointer) = self.content.pointer.take()
+        {
+            pointer.pointer().release();
+        }
+    }
+
+    fn remove_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_seat::WlSeat) {}
+}
+
+impl KeyboardHandler for Overlay<Picker> {
+    fn enter(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_keyboard::WlKeyboard,
+        _: &wl_surface::WlSurface,
+        _: u32,
+        _: &[u32],
+        _: &[Keysym],
+    ) {
+    }
+
+    fn leave(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_keyboard::WlKeyboard,
+        _: &wl_surface::WlSurface,
+        _: u32,
+    ) {
+    }
+
+    fn press_key(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_keyboard::WlKeyboard,
+        _: u32,
+        event: KeyEvent,
+    ) {
+        if event.keysym == Keysym::Escape {
+            self.content.result = Some(None);
+        }
+    }
+
+    fn repeat_key(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_keyboard::WlKeyboard,
+        _: u32,
+        _: KeyEvent,
+    ) {
+    }
+
+    fn release_key(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_keyboard::WlKeyboard,
+        _: u32,
+        _: KeyEvent,
+    ) {
+    }
+
+    fn update_modifiers(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_keyboard::WlKeyboard,
+        _: u32,
+        _: Modifiers,
+        _: RawModifiers,
+        _: u32,
+    ) {
+    }
+}
+
+impl PointerHandler for Overlay<Picker> {
+    fn pointer_frame(
+        &mut self,
+        conn: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_pointer::WlPointer,
+        events: &[PointerEvent],
+    ) {
+        let picker = &mut self.content;
+        for event in events {
+            let Some(index) = picker.surface_index(&event.surface) else {
+                continue;
+            };
+            let origin = picker.surfaces[index].origin;
+            let point = Point::new(
+                origin.x + event.position.0 as f32,
+                origin.y + event.position.1 as f32,
+            );
+            let under = hit(&picker.targets, point);
+            match event.kind {
+                PointerEventKind::Enter { .. } => {
+                    if let Some(pointer) = &picker.pointer {
+                        let _ = pointer.set_cursor(conn, CursorIcon::Crosshair);
+                    }
+                    picker.set_hovered(&mut self.painter, under);
+                }
+                PointerEventKind::Motion { .. } => picker.set_hovered(&mut self.painter, under),
+                PointerEventKind::Leave { .. } => picker.set_hovered(&mut self.painter, None),
+                PointerEventKind::Press {
+                    button: BTN_LEFT, ..
+                } => {
+                    if let Some(handle_lo) = under.and_then(|under| picker.targets[under].handle_lo)
+                    {
+                        picker.result = Some(Some(handle_lo));
+                    }
+                }
+                PointerEventKind::Press {
+                    button: BTN_RIGHT, ..
+                } => picker.result = Some(None),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl ProvidesRegistryState for Overlay<Picker> {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+    registry_handlers![OutputState, SeatState];
+}
+
+delegate_seat!(Overlay<Picker>);
+delegate_keyboard!(Overlay<Picker>);
+delegate_pointer!(Overlay<Picker>);
+delegate_registry!(Overlay<Picker>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hyprland::fixtures::{self, monitor};
+
+    fn client(address: &str, workspace: i64, floating: bool, at: [i32; 2]) -> Client {
+        Client {
+            floating,
+            at,
+            ..fixtures::client(address, workspace)
+        }
+    }
+
+    #[test]
+    fn hits_the_top_most_window_on_screen() {
+        let monitors = [monitor(0, "DP-1", 0, 1)];
+        let clients = [
+            client("0x55d0c6b1e2a0", 1, false, [0, 0]),
+            client("0x55d0c6b1f310", 1, true, [200, 100]),
+            client("0x55d0c6b203c0", 2, true, [0, 0]),
+        ];
+        // The portal's handles are its own, unrelated to the addresses.
+        let candidates = HashMap::from([(0x55d0_c6b1_e2a0, 1834059424), (0x55d0_c6b2_03c0, 7)]);
+        let targets = targets(&clients, &monitors, &candidates);
+        // The window on the hidden workspace is out; the floating one covers the tiled one,
+        // though the portal didn't offer it.
+        assert_eq!(targets.len(), 2);
+        let covered = &targets[hit(&targets, Point::new(300.0, 200.0)).unwrap()];
+        assert_eq!(covered.handle_lo, None);
+        assert_eq!(
+            targets[hit(&targets, Point::new(50.0, 50.0)).unwrap()].handle_lo,
+            Some(1834059424)
+        );
+        assert_eq!(hit(&targets, Point::new(1000.0, 1000.0)), None);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::wayland::WindowKey;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// One entry of `XDPH_WINDOW_SHARING_LIST`, the windows the portal lets the user pick from.
+#[derive(Debug, Clone)]
+pub struct PortalWindow {
+    pub handle_lo: u32,
+    pub class: String,
+    pub title: String,
+    /// The window's Hyprland address, which is how it is found in Hyprland's IPC replies.
+    pub mapped_id: u64,
+    pub group_index: usize,
+    pub group_size: usize,
+}
+
+impl PortalWindow {
+    pub fn matches(&self, key: &WindowKey) -> bool {
+        self.class == key.app_id
+            && self.title == key.title
+            && self.group_index == key.group_index
+            && self.group_size == key.group_size
+    }
+}
+
+/// The portal's window list, parsed once and shared by the UI and the Wayland thread.
+pub fn windows() -> &'static [PortalWindow] {
+    static WINDOWS: OnceLock<Vec<PortalWindow>> = OnceLock::new();
+    WINDOWS.get_or_init(|| {
+        parse_window_list(&std::env::var("XDPH_WINDOW_SHARING_LIST").unwrap_or_default())
+    })
+}
+
+/// Whether a toplevel is one the portal offered.
+pub fn is_listed(key: &WindowKey) -> bool {
+    windows().iter().any(|window| window.matches(key))
+}
+
+pub fn parse_window_list(raw: &str) -> Vec<PortalWindow> {
+    let mut entries = Vec::new();
+    let mut input = raw;
+    let mut counts: HashMap<(String, String), usize> = HashMap::new();
+    let mut temp = Vec::new();
+
+    while let Some(hc) = input.find("[HC>]") {
+        let (handle_str, rest) = input.split_at(hc);
+        let handle_lo = handle_str.trim().parse::<u32>().unwrap_or(0);
+        let rest = &rest["[HC>]".len()..];
+
+        let Some(ht_pos) = rest.find("[HT>]") else { break };
+        let (class, rest) = rest.split_at(ht_pos);
+        let rest = &rest["[HT>]".len()..];
+
+        let Some(he_pos) = rest.find("[HE>]") else { break };
+        let (title, rest) = rest.split_at(he_pos);
+        let rest = &rest["[HE>]".len()..];
+
+        let Some(ha_pos) = rest.find("[HA>]") else { break };
+        let (mapped, rest) = rest.split_at(ha_pos);
+        let rest = &rest["[HA>]".len()..];
+
+        let mapped_id = mapped.trim().parse::<u64>().unwrap_or(0);
+
+        let class = class.to_string();
+        let title = title.to_string();
+        *counts.entry((class.clone(), title.clone())).or_insert(0) += 1;
+        temp.push((handle_lo, class, title, mapped_id));
+
+        input = rest;
+    }
+
+    let mut seen: HashMap<(String, String), usize> = HashMap::new();
+    for (handle_lo, class, title, mapped_id) in temp {
+        let group_key = (class.clone(), title.clone());
+        let group_size = *counts.get(&group_key).unwrap_or(&1);
+        let entry = seen.entry(group_key).or_insert(0);
+        let group_index = *entry;
+        *entry += 1;
+
+        entries.push(PortalWindow {
+            handle_lo,
+            class,
+            title,
+            mapped_id,
+            group_index,
+            group_size,
+        });
+    }
+
+    entries
+}
@@ -0,0 +1,247 @@
+use crate::config::{ThumbnailMode, ThumbnailStyle};
+use crate::convert;
+use iced::{Rectangle, Size};
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+/// Largest size with the frame's aspect ratio that fits inside `bounds`. Never upscales, and
+/// never collapses a side to zero.
+pub fn fit_within(width: u32, height: u32, bounds: Size<u32>) -> Size<u32> {
+    if width <= bounds.width && height <= bounds.height {
+        return Size::new(width, height);
+    }
+    let scale = (f64::from(bounds.width) / f64::from(width))
+        .min(f64::from(bounds.height) / f64::from(height));
+    let side = |value: u32| ((f64::from(value) * scale).round() as u32).max(1);
+    Size::new(side(width), side(height))
+}
+
+/// Top crops shrink the source by at most this factor, so small text survives.
+const TOP_CROP_MAX_REDUCTION: u32 = 3;
+
+/// Unsharp mask radius and threshold, tuned for text at thumbnail size.
+const SHARPEN_SIGMA: f32 = 0.6;
+const SHARPEN_THRESHOLD: i32 = 2;
+
+/// A scaled frame: its size and straight-alpha RGBA pixels.
+pub type Scaled = (Size<u32>, Vec<u8>);
+
+/// Turns a converted frame into a thumbnail for `bounds`: crops it according to the style's
+/// mode, downscales it, and sharpens it if asked to. With `zoom`, the whole frame is also
+/// downscaled to fit inside it. Both are scaled from the same buffer, so the frame is never
+/// copied.
+pub fn thumbnail(
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+    bounds: Size<u32>,
+    zoom: Option<Size<u32>>,
+    style: ThumbnailStyle,
+) -> (Scaled, Option<Scaled>) {
+    let full = whole(width, height);
+    let rect = crop_rect(width, height, bounds, style.mode);
+    let fits = |bounds| fit_within(width, height, bounds) == Size::new(width, height);
+    // Converted frames always match their dimensions; anything else is passed through as is
+    // rather than panicking, and shows up as a garbled thumbnail.
+    // A frame that needs no filtering at all keeps its exact pixels.
+    if rgba.len() != width as usize * height as usize * 4
+        || (rect == full && fits(bounds) && zoom.is_none_or(fits))
+    {
+        let zoom = zoom.map(|_| (Size::new(width, height), rgba.clone()));
+        return ((Size::new(width, height), rgba), zoom);
+    }
+
+    let mut pixels = rgba;
+    map_pixels(&mut pixels, convert::premultiply);
+    let image = RgbaImage::from_raw(width, height, pixels).expect("length checked above");
+    let zoom = zoom.map(|zoom| downscale(&image, full, zoom, style.sharpen));
+    (downscale(&image, rect, bounds, style.sharpen), zoom)
+}
+
+/// The part of a `width`×`height` frame that a mode keeps.
+pub fn crop_rect(width: u32, height: u32, bounds: Size<u32>, mode: ThumbnailMode) -> Rectangle<u32> {
+    let full = whole(width, height);
+    if bounds.width == 0 || bounds.height == 0 {
+        return full;
+    }
+    let aspect = f64::from(bounds.width) / f64::from(bounds.height);
+    let height_for = |w: u32| ((f64::from(w) / aspect).round() as u32).clamp(1, height);
+    let width_for = |h: u32| ((f64::from(h) * aspect).round() as u32).clamp(1, width);
+
+    match mode {
+        ThumbnailMode::Fit => full,
+        ThumbnailMode::FillCrop => {
+            let (crop_width, crop_height) = if f64::from(width) / f64::from(height) > aspect {
+                (width_for(height), height)
+            } else {
+                (width, height_for(width))
+            };
+            Rectangle {
+                x: (width - crop_width) / 2,
+                y: (height - crop_height) / 2,
+                width: crop_width,
+                height: crop_height,
+            }
+        }
+        ThumbnailMode::TopCrop => {
+            let crop_width = width.min(bounds.width.saturating_mul(TOP_CROP_MAX_REDUCTION));
+            let crop_height = height_for(crop_width);
+            Rectangle {
+                x: 0,
+                y: 0,
+                width: crop_width.min(width_for(crop_height)),
+                height: crop_height,
+            }
+        }
+    }
+}
+
+fn whole(width: u32, height: u32) -> Rectangle<u32> {
+    Rectangle {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    }
+}
+
+/// Downscales the `rect` part of a premultiplied frame to fit inside `bounds`, returning
+/// straight-alpha pixels.
+///
+/// Large reductions are area-averaged down to twice the target first, then finished with
+/// Lanczos3, which keeps small text legible without paying for a huge Lanczos kernel. Both
+/// filters (and the optional unsharp mask) run on premultiplied pixels so transparent areas
+/// can't darken the edges around them.
+fn downscale(image: &RgbaImage, rect: Rectangle<u32>, bounds: Size<u32>, sharpen: bool) -> Scaled {
+    let view = imageops::crop_imm(image, rect.x, rect.y, rect.width, rect.height);
+    let target = fit_within(rect.width, rect.height, bounds);
+    let scaled = if target == Size::new(rect.width, rect.height) {
+        view.to_image()
+    } else {
+        let (coarse_width, coarse_height) = (target.width * 2, target.height * 2);
+        let image = if rect.width > coarse_width && rect.height > coarse_height {
+            let coarse = imageops::thumbnail(&*view, coarse_width, coarse_height);
+            imageops::resize(&coarse, target.width, target.height, FilterType::Lanczos3)
+        } else {
+            imageops::resize(&*view, target.width, target.height, FilterType::Lanczos3)
+        };
+        if sharpen {
+            imageops::unsharpen(&image, SHARPEN_SIGMA, SHARPEN_THRESHOLD)
+        } else {
+            image
+        }
+    };
+    let mut pixels = scaled.into_raw();
+    map_pixels(&mut pixels, convert::unpremultiply);
+    (target, pixels)
+}
+
+fn map_pixels(rgba: &mut [u8], f: fn([u8; 4]) -> [u8; 4]) {
+    for px in rgba.chunks_exact_mut(4) {
+        let mapped = f([px[0], px[1], px[2], px[3]]);
+        px.copy_from_slice(&mapped);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(mode: ThumbnailMode) -> ThumbnailStyle {
+        ThumbnailStyle {
+            mode,
+            sharpen: false,
+            contrast_frame: false,
+        }
+    }
+
+    #[test]
+    fn fits_preserving_aspect_ratio() {
+        let bounds = Size::new(220, 140);
+        assert_eq!(fit_within(3840, 2160, bounds), Size::new(220, 124));
+        assert_eq!(fit_within(1000, 2000, bounds), Size::new(70, 140));
+        assert_eq!(fit_within(100, 50, bounds), Size::new(100, 50));
+        assert_eq!(fit_within(10000, 1, bounds), Size::new(220, 1));
+    }
+
+    #[test]
+    fn transparent_neighbours_do_not_darken_edges() {
+        // Left half opaque-ish red at half alpha, right half fully transparent.
+        let (width, height) = (64, 8);
+        let mut rgba = Vec::new();
+        for _ in 0..height {
+            for x in 0..width {
+                let px = if x < width / 2 { [0xff, 0, 0, 0x80] } else { [0, 0, 0, 0] };
+                rgba.extend_from_slice(&px);
+            }
+        }
+        let fit = style(ThumbnailMode::Fit);
+        let ((size, scaled), _) = thumbnail(rgba, width, height, Size::new(8, 8), None, fit);
+        assert_eq!(size, Size::new(8, 1));
+        for px in scaled.chunks_exact(4).filter(|px| px[3] > 0x10) {
+            assert!(px[0] > 0xf0, "edge pixel darkened: {px:?}");
+        }
+    }
+
+    #[test]
+    fn crops_match_the_tile_aspect() {
+        let bounds = Size::new(220, 140);
+        let rect = |x, y, width, height| Rectangle {
+            x,
+            y,
+            width,
+            height,
+        };
+        assert_eq!(
+            crop_rect(3840, 2160, bounds, ThumbnailMode::Fit),
+            rect(0, 0, 3840, 2160)
+        );
+        assert_eq!(
+            crop_rect(3840, 2160, bounds, ThumbnailMode::FillCrop),
+            rect(223, 0, 3394, 2160)
+        );
+        assert_eq!(
+            crop_rect(1000, 2000, bounds, ThumbnailMode::FillCrop),
+            rect(0, 682, 1000, 636)
+        );
+        // At most 3:1 reduction, anchored at the top-left corner.
+        assert_eq!(
+            crop_rect(3840, 2160, bounds, ThumbnailMode::TopCrop),
+            rect(0, 0, 660, 420)
+        );
+        // A short, wide window runs out of height before width.
+        assert_eq!(
+            crop_rect(3840, 200, bounds, ThumbnailMode::TopCrop),
+            rect(0, 0, 314, 200)
+        );
+    }
+
+    #[test]
+    fn crops_the_thumbnail_but_not_the_zoom_copy() {
+        // 3x1 frame whose red channel is the pixel index.
+        let rgba: Vec<u8> = (0..3).flat_map(|i| [i, 0, 0, 0xff]).collect();
+        let (grid, zoom) = thumbnail(
+            rgba.clone(),
+            3,
+            1,
+            Size::new(1, 1),
+            Some(Size::new(4, 4)),
+            style(ThumbnailMode::FillCrop),
+        );
+        assert_eq!(grid, (Size::new(1, 1), vec![1, 0, 0, 0xff]));
+        assert_eq!(zoom, Some((Size::new(3, 1), rgba)));
+    }
+
+    #[test]
+    fn small_frames_pass_through() {
+        let rgba = vec![1, 2, 3, 4];
+        let style = ThumbnailStyle {
+            sharpen: true,
+            ..style(ThumbnailMode::Fit)
+        };
+        assert_eq!(
+            thumbnail(rgba.clone(), 1, 1, Size::new(220, 140), None, style),
+            ((Size::new(1, 1), rgba), None)
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashSet;
+
+/// How urgently a toplevel wants its thumbnail. Only [`Priority::Visible`] and above are
+/// started; the rest stay queued until the UI scrolls them into view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Not in the portal's list, or hidden by a rule. Never captured, and dropped from the
+    /// queue.
+    Skip,
+    /// Listed, but scrolled out of view.
+    Deferred,
+    Visible,
+    /// The compositor's active window, most likely the one being shared.
+    Focused,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureRequest {
+    pub toplevel_id: u32,
+    /// Zero-based attempt number, carried through timeout retries.
+    pub attempt: u32,
+}
+
+/// Sits between capture requests and the compositor: caps how many frames are in flight and
+/// starts the most urgent waiting toplevel first, in request order among equals.
+///
+/// Priorities are given with each request and kept until [`CaptureScheduler::reprioritize`],
+/// so picking the next capture doesn't re-examine every waiting toplevel.
+pub struct CaptureScheduler {
+    max_in_flight: usize,
+    waiting: Vec<(CaptureRequest, Priority)>,
+    in_flight: HashSet<u32>,
+}
+
+impl CaptureScheduler {
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight: max_in_flight.max(1),
+            waiting: Vec::new(),
+            in_flight: HashSet::new(),
+        }
+    }
+
+    /// Queues a capture, replacing any request already waiting for the same toplevel.
+    pub fn enqueue(&mut self, request: CaptureRequest, priority: Priority) {
+        match self
+            .waiting
+            .iter_mut()
+            .find(|(waiting, _)| waiting.toplevel_id == request.toplevel_id)
+        {
+            Some(waiting) => *waiting = (request, priority),
+            None => self.waiting.push((request, priority)),
+        }
+    }
+
+    /// Frees the in-flight slot held by a toplevel.
+    pub fn finished(&mut self, toplevel_id: u32) {
+        self.in_flight.remove(&toplevel_id);
+    }
+
+    /// Drops everything known about a toplevel that went away.
+    pub fn forget(&mut self, toplevel_id: u32) {
+        self.finished(toplevel_id);
+        self.waiting.retain(|(waiting, _)| waiting.toplevel_id != toplevel_id);
+    }
+
+    pub fn waiting(&self) -> impl Iterator<Item = u32> + '_ {
+        self.waiting.iter().map(|(waiting, _)| waiting.toplevel_id)
+    }
+
+    /// Replaces the priorities of the waiting requests, given in [`CaptureScheduler::waiting`]
+    /// order, and drops the requests that became [`Priority::Skip`]. Returns their toplevels.
+    pub fn reprioritize(&mut self, priorities: &[Priority]) -> Vec<u32> {
+        debug_assert_eq!(priorities.len(), self.waiting.len());
+        for ((_, priority), &updated) in self.waiting.iter_mut().zip(priorities) {
+            *priority = updated;
+        }
+        let mut dropped = Vec::new();
+        self.waiting.retain(|(waiting, priority)| {
+            let keep = *priority != Priority::Skip;
+            if !keep {
+                dropped.push(waiting.toplevel_id);
+            }
+            keep
+        });
+        dropped
+    }
+
+    /// Takes the next request to start, if a slot is free and anything is worth starting.
+    pub fn next(&mut self) -> Option<CaptureRequest> {
+        if self.in_flight.len() >= self.max_in_flight {
+            return None;
+        }
+        let (index, _) = self
+            .waiting
+            .iter()
+            .enumerate()
+            // One frame per toplevel at a time; a recapture waits for the current one.
+            .filter(|(_, (waiting, _))| !self.in_flight.contains(&waiting.toplevel_id))
+            .map(|(index, (_, priority))| (index, *priority))
+            .filter(|(_, priority)| *priority >= Priority::Visible)
+            // max_by_key keeps the last maximum; reverse so the earliest request wins ties.
+            .rev()
+            .max_by_key(|(_, priority)| *priority)?;
+        let (request, _) = self.waiting.remove(index);
+        self.in_flight.insert(request.toplevel_id);
+        Some(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(toplevel_id: u32) -> CaptureRequest {
+        CaptureRequest {
+            toplevel_id,
+            attempt: 0,
+        }
+    }
+
+    #[test]
+    fn caps_in_flight_captures() {
+        let mut scheduler = CaptureScheduler::new(2);
+        for id in 1..=3 {
+            scheduler.enqueue(request(id), Priority::Visible);
+        }
+        assert_eq!(scheduler.next(), Some(request(1)));
+        assert_eq!(scheduler.next(), Some(request(2)));
+        assert_eq!(scheduler.next(), None);
+
+        scheduler.finished(1);
+        assert_eq!(scheduler.next(), Some(request(3)));
+    }
+
+    #[test]
+    fn recapture_waits_for_the_frame_in_flight() {
+        let mut scheduler = CaptureScheduler::new(4);
+        scheduler.enqueue(request(1), Priority::Visible);
+        assert_eq!(scheduler.next(), Some(request(1)));
+        scheduler.enqueue(request(1), Priority::Visible);
+        assert_eq!(scheduler.next(), None);
+
+        scheduler.finished(1);
+        assert_eq!(scheduler.next(), Some(request(1)));
+    }
+
+    #[test]
+    fn focused_jumps_the_queue_and_offscreen_waits() {
+        let mut scheduler = CaptureScheduler::new(4);
+        let priorities = [
+            Priority::Deferred,
+            Priority::Deferred,
+            Priority::Visible,
+            Priority::Focused,
+        ];
+        for (id, priority) in (1..=4).zip(priorities) {
+            scheduler.enqueue(request(id), priority);
+        }
+        assert_eq!(scheduler.next(), Some(request(4)));
+        assert_eq!(scheduler.next(), Some(request(3)));
+        assert_eq!(scheduler.next(), None);
+        assert_eq!(scheduler.waiting().collect::<Vec<_>>(), [1, 2]);
+
+        // Scrolling the deferred tiles into view lets them start; a skipped one is dropped.
+        let dropped = scheduler.reprioritize(&[Priority::Skip, Priority::Visible]);
+        assert_eq!(dropped, [1]);
+        assert_eq!(scheduler.next(), Some(request(2)));
+        assert_eq!(scheduler.waiting().count(), 0);
+    }
+
+    #[test]
+    fn requeueing_replaces_and_forgetting_removes() {
+        let mut scheduler = CaptureScheduler::new(1);
+        scheduler.enqueue(request(7), Priority::Deferred);
+        scheduler.enqueue(
+            CaptureRequest {
+                toplevel_id: 7,
+                attempt: 2,
+            },
+            Priority::Visible,
+        );
+        scheduler.enqueue(request(8), Priority::Visible);
+        scheduler.forget(8);
+        assert_eq!(
+            scheduler.next(),
+            Some(CaptureRequest {
+                toplevel_id: 7,
+                attempt: 2
+            })
+        );
+        assert_eq!(scheduler.waiting().count(), 0);
+    }
+}
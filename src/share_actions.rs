@@ -0,0 +1,114 @@
+use crate::config::ShareAction;
+use crate::hyprland::{self, Client, Monitor};
+
+/// Hyprland dispatchers that carry out `actions` on a window, each with the action it
+/// belongs to. Actions that are already satisfied, including by an earlier action, produce
+/// nothing, so a window that is on screen and windowed is left alone.
+pub fn plan(
+    actions: &[ShareAction],
+    client: &Client,
+    monitors: &[Monitor],
+) -> Vec<(ShareAction, String)> {
+    let address = &client.address;
+    let mut workspace = client.workspace.clone();
+    let mut dispatchers = Vec::new();
+    for &action in actions {
+        match action {
+            ShareAction::BringHere => {
+                let Some(here) = monitors.iter().find(|monitor| monitor.focused) else {
+                    continue;
+                };
+                if workspace.id != here.active_workspace.id {
+                    workspace = here.active_workspace.clone();
+                    let dispatcher =
+                        format!("movetoworkspacesilent {},address:{address}", workspace.id);
+                    dispatchers.push((action, dispatcher));
+                }
+            }
+            ShareAction::ShowSpecial => {
+                if workspace.id < 0 && !monitors.iter().any(|monitor| monitor.shows(workspace.id)) {
+                    // `special:magic` is toggled as `magic`; the unnamed one takes no argument.
+                    let name = workspace.name.strip_prefix("special:").unwrap_or("");
+                    dispatchers.push((action, format!("togglespecialworkspace {name}")));
+                }
+            }
+            ShareAction::Unfullscreen => {
+                if client.fullscreen.is_fullscreen() {
+                    // `fullscreenstate` only acts on the focused window.
+                    dispatchers.push((action, format!("focuswindow address:{address}")));
+                    dispatchers.push((action, "fullscreenstate 0 0".to_string()));
+                }
+            }
+        }
+    }
+    dispatchers
+}
+
+/// Runs the actions on the window with this mapped id against Hyprland's current state.
+/// Failures are reported on stderr; the share goes ahead either way.
+pub fn run(actions: &[ShareAction], mapped_id: u64) {
+    let state = hyprland::clients().and_then(|clients| Ok((clients, hyprland::monitors()?)));
+    let (clients, monitors) = match state {
+        Ok(state) => state,
+        Err(error) => {
+            eprintln!("Pre-share actions skipped: Hyprland IPC: {error}");
+            return;
+        }
+    };
+    let Some(client) = clients.iter().find(|client| client.id() == Some(mapped_id)) else {
+        eprintln!("Pre-share actions skipped: Hyprland doesn't know the window");
+        return;
+    };
+    for (action, dispatcher) in plan(actions, client, &monitors) {
+        if let Err(error) = hyprland::dispatch(&dispatcher) {
+            eprintln!("Pre-share action {action:?} failed: {dispatcher}: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hyprland::fixtures::{self, monitor};
+    use crate::hyprland::Fullscreen;
+
+    fn client(workspace: i64, fullscreen: u8) -> Client {
+        Client {
+            fullscreen: Fullscreen::Mode(fullscreen),
+            ..fixtures::client("0xabc", workspace)
+        }
+    }
+
+    #[test]
+    fn plans_only_what_is_needed() {
+        let mut monitors = [monitor(0, "DP-1", 0, 2)];
+        monitors[0].focused = true;
+        let all = [
+            ShareAction::BringHere,
+            ShareAction::ShowSpecial,
+            ShareAction::Unfullscreen,
+        ];
+
+        let commands = |actions: &[ShareAction], client: &Client| -> Vec<String> {
+            plan(actions, client, &monitors)
+                .into_iter()
+                .map(|(_, command)| command)
+                .collect()
+        };
+        let scratchpad = client(-98, 2);
+        // Once moved, the special workspace needn't be opened.
+        assert_eq!(
+            commands(&all, &scratchpad),
+            [
+                "movetoworkspacesilent 2,address:0xabc",
+                "focuswindow address:0xabc",
+                "fullscreenstate 0 0",
+            ]
+        );
+        assert_eq!(
+            commands(&[ShareAction::ShowSpecial], &scratchpad),
+            ["togglespecialworkspace magic"]
+        );
+        assert!(plan(&all, &client(2, 0), &monitors).is_empty());
+    }
+}
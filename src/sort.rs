@@ -0,0 +1,159 @@
+use crate::hyprland::Client;
+use crate::state::SortMode;
+use std::cmp::Ordering;
+
+/// What a window is sorted by. Everything but the portal position is optional, since
+/// Hyprland's IPC or the compositor's focus events may not be available.
+pub struct SortFields<'a> {
+    /// Position in `XDPH_WINDOW_SHARING_LIST`, the final tie-breaker for every mode.
+    pub portal_index: usize,
+    pub title: &'a str,
+    /// What the tile shows as the application: the desktop entry name, else the class.
+    pub app: &'a str,
+    /// Increases each time a window gains focus while the picker is open.
+    pub activation: Option<u64>,
+    pub client: Option<&'a Client>,
+}
+
+/// Orders two windows under `mode`. Windows that compare equal keep their portal order, so
+/// the result never depends on the previous order.
+pub fn compare(mode: SortMode, a: &SortFields<'_>, b: &SortFields<'_>) -> Ordering {
+    let by_mode = match mode {
+        SortMode::Portal => Ordering::Equal,
+        SortMode::RecentFocus => b
+            .activation
+            .cmp(&a.activation)
+            .then_with(|| missing_last(focus_history(a), focus_history(b))),
+        SortMode::Title => caseless(a.title, b.title),
+        SortMode::Class => caseless(a.app, b.app).then_with(|| caseless(a.title, b.title)),
+        SortMode::Workspace => missing_last(workspace_rank(a), workspace_rank(b)),
+        SortMode::Stacking => missing_last(stacking_rank(a), stacking_rank(b)),
+    };
+    by_mode.then(a.portal_index.cmp(&b.portal_index))
+}
+
+fn caseless(a: &str, b: &str) -> Ordering {
+    a.to_lowercase().cmp(&b.to_lowercase())
+}
+
+/// Orders known values ascending, ahead of unknown ones.
+fn missing_last<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn focus_history(fields: &SortFields<'_>) -> Option<i64> {
+    fields.client?.focus_history_id.filter(|id| *id >= 0)
+}
+
+/// Numbered workspaces in order, then special (scratchpad) ones.
+fn workspace_rank(fields: &SortFields<'_>) -> Option<(bool, i64)> {
+    let id = fields.client?.workspace.id;
+    Some((id <= 0, id.abs()))
+}
+
+fn stacking_rank(fields: &SortFields<'_>) -> Option<(u8, i64)> {
+    fields.client.map(stacking_order)
+}
+
+/// Hyprland's IPC has no stacking order, so approximate it the way Hyprland draws: pinned
+/// windows over fullscreen ones over floating ones over tiled ones, and within each layer the
+/// most recently focused (which Hyprland raises) on top. Lower is higher up.
+pub fn stacking_order(client: &Client) -> (u8, i64) {
+    let layer = if client.pinned {
+        0
+    } else if client.fullscreen.is_fullscreen() {
+        1
+    } else if client.floating {
+        2
+    } else {
+        3
+    };
+    (layer, client.focus_history_id.filter(|id| *id >= 0).unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hyprland::{self, fixtures};
+    use crate::portal;
+
+    fn client(workspace: i64, floating: bool, focus_history_id: i64) -> Client {
+        Client {
+            floating,
+            focus_history_id: Some(focus_history_id),
+            ..fixtures::client("0x1", workspace)
+        }
+    }
+
+    fn order(mode: SortMode, windows: &[SortFields<'_>]) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..windows.len()).collect();
+        indices.sort_by(|&a, &b| compare(mode, &windows[a], &windows[b]));
+        indices
+    }
+
+    #[test]
+    fn sorts_by_each_mode_with_portal_order_breaking_ties() {
+        let clients = [client(2, false, 2), client(-98, true, 0), client(1, true, 1)];
+        let fields = |portal_index: usize, title, app, activation| SortFields {
+            portal_index,
+            title,
+            app,
+            activation,
+            client: clients.get(portal_index),
+        };
+        let windows = [
+            fields(0, "beta", "Zed", None),
+            fields(1, "Alpha", "kitty", None),
+            fields(2, "alpha", "Kitty", Some(1)),
+            fields(3, "gamma", "zed", None),
+        ];
+        assert_eq!(order(SortMode::Portal, &windows), [0, 1, 2, 3]);
+        assert_eq!(order(SortMode::RecentFocus, &windows), [2, 1, 0, 3]);
+        assert_eq!(order(SortMode::Title, &windows), [1, 2, 0, 3]);
+        assert_eq!(order(SortMode::Class, &windows), [1, 2, 0, 3]);
+        assert_eq!(order(SortMode::Workspace, &windows), [2, 0, 1, 3]);
+        assert_eq!(order(SortMode::Stacking, &windows), [1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn reads_hyprland_fields_of_the_client_behind_each_portal_window() {
+        // XDPH's handles are unrelated to the Hyprland addresses the mapped ids carry.
+        let windows = portal::parse_window_list(
+            "1834059424[HC>]kitty[HT>]~[HE>]94355175105184[HA>]\
+             1834061232[HC>]firefox[HT>]Example[HE>]94355175109392[HA>]",
+        );
+        let clients = hyprland::for_portal_windows(
+            &windows,
+            vec![
+                Client {
+                    focus_history_id: Some(0),
+                    ..fixtures::client("0x55d0c6b1f310", 1)
+                },
+                Client {
+                    focus_history_id: Some(1),
+                    ..fixtures::client("0x55d0c6b1e2a0", 4)
+                },
+            ],
+        );
+        let fields: Vec<SortFields<'_>> = windows
+            .iter()
+            .zip(&clients)
+            .enumerate()
+            .map(|(portal_index, (window, client))| SortFields {
+                portal_index,
+                title: &window.title,
+                app: &window.class,
+                activation: None,
+                client: client.as_ref(),
+            })
+            .collect();
+        assert_eq!(order(SortMode::Workspace, &fields), [1, 0]);
+        assert_eq!(order(SortMode::RecentFocus, &fields), [1, 0]);
+        assert_eq!(order(SortMode::Stacking, &fields), [1, 0]);
+    }
+}
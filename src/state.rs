@@ -0,0 +1,193 @@
+use crate::config::APP_DIR;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+
+const STATE_FILE: &str = "state.toml";
+
+/// Tile zoom bounds, in percent.
+pub const MIN_TILE_ZOOM: u32 = 50;
+pub const MAX_TILE_ZOOM: u32 = 200;
+
+/// Picker settings chosen in the UI and remembered between launches, in
+/// `$XDG_STATE_HOME/hyprland-better-share-picker/state.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct UiState {
+    pub layout: Layout,
+    /// Tile size as a percentage of the layout's default, adjusted with Ctrl+scroll.
+    pub tile_zoom: u32,
+    pub sort: SortMode,
+}
+
+impl UiState {
+    fn clamped(self) -> Self {
+        Self {
+            tile_zoom: self.tile_zoom.clamp(MIN_TILE_ZOOM, MAX_TILE_ZOOM),
+            ..self
+        }
+    }
+}
+
+impl Default for UiState {
+    fn default() -> Self {
+        Self {
+            layout: Layout::default(),
+            tile_zoom: 100,
+            sort: SortMode::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    /// Wrapped cards with a thumbnail, title and class.
+    #[default]
+    Grid,
+    /// One window per line: a small thumbnail, then title, class and workspace.
+    List,
+    /// Bare thumbnails, titles in tooltips. Child windows get tiles of their own.
+    Compact,
+    /// Monitors drawn to scale with each window at its real position and size, one page per
+    /// workspace. Needs Hyprland's IPC.
+    Overview,
+}
+
+/// Order of the tiles; see [`crate::sort`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortMode {
+    /// As `XDPH_WINDOW_SHARING_LIST` lists them.
+    #[default]
+    Portal,
+    RecentFocus,
+    Title,
+    Class,
+    Workspace,
+    /// Top-most window first.
+    Stacking,
+}
+
+impl SortMode {
+    pub const ALL: [SortMode; 6] = [
+        SortMode::Portal,
+        SortMode::RecentFocus,
+        SortMode::Title,
+        SortMode::Class,
+        SortMode::Workspace,
+        SortMode::Stacking,
+    ];
+
+    /// The next mode, for cycling with a shortcut.
+    pub fn next(self) -> SortMode {
+        let index = Self::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+impl fmt::Display for SortMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SortMode::Portal => "Portal order",
+            SortMode::RecentFocus => "Recently focused",
+            SortMode::Title => "Title",
+            SortMode::Class => "Application",
+            SortMode::Workspace => "Workspace",
+            SortMode::Stacking => "Stacking order",
+        })
+    }
+}
+
+/// Reads the saved state. A missing or unreadable file gives the defaults, and values edited
+/// out of range are pulled back into it.
+pub fn load() -> UiState {
+    read::<UiState>(STATE_FILE).clamped()
+}
+
+/// Best effort: a state file that can't be written only costs the user their layout.
+pub fn save(state: &UiState) {
+    write(STATE_FILE, state);
+}
+
+/// Reads a TOML file from the state directory, falling back to the defaults if it is missing
+/// or broken.
+pub fn read<T: DeserializeOwned + Default>(file_name: &str) -> T {
+    let Some(path) = state_dir().map(|dir| dir.join(file_name)) else {
+        return T::default();
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return T::default(),
+        Err(error) => {
+            eprintln!("State error: failed to read {}: {error}", path.display());
+            return T::default();
+        }
+    };
+    toml::from_str(&contents).unwrap_or_else(|error| {
+        eprintln!("State error: {}: {error}", path.display());
+        T::default()
+    })
+}
+
+/// Writes a TOML file to the state directory, reporting failures on stderr.
+pub fn write<T: Serialize>(file_name: &str, value: &T) {
+    let Some(path) = state_dir().map(|dir| dir.join(file_name)) else { return };
+    let result = toml::to_string(value)
+        .map_err(|error| error.to_string())
+        .and_then(|contents| write_atomically(&path, &contents).map_err(|error| error.to_string()));
+    if let Err(error) = result {
+        eprintln!("State error: failed to write {}: {error}", path.display());
+    }
+}
+
+/// Writes through a temporary file, so a crash mid-write can't leave a truncated file.
+fn write_atomically(path: &std::path::Path, contents: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp = path.with_extension("tmp");
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path)
+}
+
+/// `$XDG_STATE_HOME/hyprland-better-share-picker`, falling back to `~/.local/state/...`.
+fn state_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_STATE_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+    Some(base.join(APP_DIR))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_clamps_the_zoom() {
+        let state = UiState {
+            layout: Layout::Compact,
+            tile_zoom: 150,
+            sort: SortMode::RecentFocus,
+        };
+        let saved = toml::to_string(&state).unwrap();
+        assert_eq!(toml::from_str::<UiState>(&saved).unwrap(), state);
+
+        let edited: UiState = toml::from_str("tile-zoom = 5000").unwrap();
+        assert_eq!(edited.clamped().tile_zoom, MAX_TILE_ZOOM);
+        let edited: UiState = toml::from_str("tile-zoom = 0\nlayout = \"list\"").unwrap();
+        assert_eq!(
+            edited.clamped(),
+            UiState {
+                layout: Layout::List,
+                tile_zoom: MIN_TILE_ZOOM,
+                ..UiState::default()
+            }
+        );
+    }
+}
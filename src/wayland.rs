@@ -1,15 +1,27 @@
-use iced::futures::channel::mpsc;
-use iced::futures::{SinkExt, StreamExt};
-use iced::stream;
-use iced::Subscription;
+use bytes::Bytes;
+use iced::{Size, Subscription};
 use smithay_client_toolkit::error::GlobalError;
 use smithay_client_toolkit::globals::ProvidesBoundGlobal;
+use smithay_client_toolkit::reexports::calloop::channel;
+use smithay_client_toolkit::reexports::calloop::timer::{TimeoutAction, Timer};
+use smithay_client_toolkit::reexports::calloop::{EventLoop, LoopHandle, RegistrationToken};
+use smithay_client_toolkit::reexports::calloop_wayland_source::WaylandSource;
 use smithay_client_toolkit::shm::slot::{Buffer, SlotPool};
-use std::collections::HashMap;
+use crate::config::{self, ThumbnailStyle};
+use crate::convert::{self, FrameError, FrameLayout};
+use crate::event_queue;
+use crate::hyprland;
+use crate::memory;
+use crate::portal;
+use crate::resample;
+use crate::scheduler::{CaptureRequest, CaptureScheduler, Priority};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::sync::OnceLock;
+use std::time::Duration;
 use wayland_client::globals::registry_queue_init;
 use wayland_client::globals::GlobalListContents;
-use wayland_client::protocol::{wl_registry, wl_shm};
+use wayland_client::protocol::{wl_registry, wl_seat, wl_shm};
 use wayland_client::{Connection, Dispatch, Proxy, QueueHandle, WEnum};
 use wayland_protocols_wlr::foreign_toplevel::v1::client::{
     zwlr_foreign_toplevel_handle_v1, zwlr_foreign_toplevel_manager_v1,
@@ -24,31 +36,173 @@ use protocol::hyprland_toplevel_export::hyprland_toplevel_export_manager_v1;
 
 #[derive(Debug, Clone)]
 pub enum WaylandEvent {
+    /// The Wayland thread is up and accepts commands through this handle.
+    Connected(WaylandHandle),
     Upsert { id: u32, title: String, app_id: String },
     Remove { id: u32 },
     Thumbnail {
-        title: String,
-        app_id: String,
-        group_index: usize,
-        group_size: usize,
+        key: WindowKey,
         width: u32,
         height: u32,
-        rgba: Vec<u8>,
+        /// Shared with the `image::Handle` built from it, never copied on the way.
+        rgba: Bytes,
+        /// Larger copy for the zoom pane, when the window has more detail than the grid
+        /// thumbnail shows.
+        zoom: Option<(Size<u32>, Bytes)>,
     },
+    Parent {
+        child: WindowKey,
+        parent: Option<WindowKey>,
+    },
+    /// The window gained keyboard focus in the compositor.
+    Activated { key: WindowKey },
+    CaptureFailed {
+        key: WindowKey,
+        error: CaptureError,
+    },
+    /// The initial set of toplevels (with titles and app IDs) has been announced.
+    ToplevelsEnumerated,
     Error { message: String },
 }
 
+/// Requests from the UI to the Wayland thread.
+#[derive(Debug, Clone)]
+pub enum WaylandCommand {
+    /// The windows whose tiles are on screen, or about to be; their captures go first.
+    SetVisible(Vec<WindowKey>),
+    /// Physical pixel sizes to render thumbnails at. Nothing is captured until they are
+    /// known; a later change regenerates every thumbnail.
+    SetThumbnailSize(ThumbnailSizes),
+    /// Capture these windows again; the UI evicted their thumbnails to stay within budget.
+    Recapture(Vec<WindowKey>),
+    /// Whether captures include the cursor. Changing it recaptures every window.
+    SetOverlayCursor(bool),
+    /// Focus this window in the compositor, then capture it again once it has had time to
+    /// come on screen.
+    Activate(WindowKey),
+}
+
+/// Physical pixel bounds of the picture areas, i.e. their logical size times the picker's
+/// scale factor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThumbnailSizes {
+    /// A grid tile's thumbnail area.
+    pub grid: Size<u32>,
+    /// The zoom pane, if it is enabled; each capture then also yields a mid-resolution copy.
+    pub zoom: Option<Size<u32>>,
+}
+
+/// Sending half of the command channel into the Wayland event loop.
+#[derive(Clone)]
+pub struct WaylandHandle(channel::Sender<WaylandCommand>);
+
+impl WaylandHandle {
+    /// Best effort: commands sent after the Wayland thread exited are dropped.
+    pub fn send(&self, command: WaylandCommand) {
+        let _ = self.0.send(command);
+    }
+}
+
+impl fmt::Debug for WaylandHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WaylandHandle")
+    }
+}
+
+/// Why a single window's thumbnail could not be produced. Failures stay per-window; they never
+/// take down the Wayland thread.
+#[derive(Debug, Clone)]
+pub enum CaptureError {
+    /// The compositor answered the capture with `failed`.
+    Failed,
+    /// None of the offered shm buffer formats can be converted.
+    UnsupportedFormat(Vec<WEnum<wl_shm::Format>>),
+    /// The compositor only offered linux-dmabuf buffers, which we can't read yet.
+    DmabufOnly,
+    /// Buffer metadata was inconsistent or over the size limit.
+    InvalidFrame(FrameError),
+    /// Allocating or mapping shared memory failed.
+    Shm(String),
+    /// The compositor broke the frame protocol, e.g. `ready` before a buffer was attached.
+    Protocol(&'static str),
+    /// No `ready` or `failed` arrived for any attempt.
+    TimedOut { attempts: u32 },
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Failed => write!(f, "compositor reported the capture as failed"),
+            CaptureError::UnsupportedFormat(offered) if offered.is_empty() => {
+                write!(f, "no shm buffer format offered")
+            }
+            CaptureError::UnsupportedFormat(offered) => {
+                write!(f, "unsupported shm buffer format(s) offered: {offered:?}")
+            }
+            CaptureError::DmabufOnly => write!(f, "compositor only offered dmabuf buffers"),
+            CaptureError::InvalidFrame(error) => write!(f, "invalid frame: {error}"),
+            CaptureError::Shm(error) => write!(f, "shared memory error: {error}"),
+            CaptureError::Protocol(error) => write!(f, "protocol error: {error}"),
+            CaptureError::TimedOut { attempts } => {
+                write!(f, "compositor never answered ({attempts} attempts)")
+            }
+        }
+    }
+}
+
+/// Best-effort identity of a toplevel as the portal sees it: `(class, title)` plus the
+/// position among duplicates, in announcement order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WindowKey {
+    pub app_id: String,
+    pub title: String,
+    pub group_index: usize,
+    pub group_size: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct WindowThumbnail {
     pub handle: iced::widget::image::Handle,
+    pub width: u32,
+    pub height: u32,
+    /// Mid-resolution copy for the zoom pane, from the same capture.
+    pub zoom: Option<Box<WindowThumbnail>>,
 }
 
 impl WindowThumbnail {
-    pub fn new(width: u32, height: u32, rgba: Vec<u8>) -> Self {
+    pub fn new(width: u32, height: u32, rgba: Bytes) -> Self {
         Self {
             handle: iced::widget::image::Handle::from_rgba(width, height, rgba),
+            width,
+            height,
+            zoom: None,
         }
     }
+
+    pub fn with_zoom(mut self, zoom: Option<(Size<u32>, Bytes)>) -> Self {
+        self.zoom = zoom.map(|(size, rgba)| Box::new(Self::new(size.width, size.height, rgba)));
+        self
+    }
+
+    /// The sharpest copy available for the zoom pane.
+    pub fn zoomed(&self) -> &WindowThumbnail {
+        self.zoom.as_deref().unwrap_or(self)
+    }
+
+    /// Decoded size in bytes, zoom copy included, as counted against the memory budget.
+    pub fn byte_len(&self) -> usize {
+        let zoom = self.zoom.as_ref().map_or(0, |zoom| zoom.byte_len());
+        self.width as usize * self.height as usize * 4 + zoom
+    }
+
+    /// Size of the thumbnail when scaled to fit inside `bounds`, preserving aspect ratio.
+    pub fn fitted_size(&self, bounds: iced::Size) -> iced::Size {
+        if self.width == 0 || self.height == 0 {
+            return bounds;
+        }
+        let scale = (bounds.width / self.width as f32).min(bounds.height / self.height as f32);
+        iced::Size::new(self.width as f32 * scale, self.height as f32 * scale)
+    }
 }
 
 pub fn subscription() -> Subscription<WaylandEvent> {
@@ -64,31 +218,48 @@ fn debug_enabled() -> bool {
     })
 }
 
-fn debug_log(message: &str) {
+pub fn debug_log(message: &str) {
     if debug_enabled() {
         eprintln!("{message}");
     }
 }
 
-fn wayland_stream() -> impl iced::futures::Stream<Item = WaylandEvent> {
-    stream::channel(100, |mut output: iced::futures::channel::mpsc::Sender<WaylandEvent>| async move {
-        let (tx, mut rx) = mpsc::unbounded::<WaylandEvent>();
+/// Events in flight between the Wayland thread and the UI. Thumbnails for the same window
+/// coalesce, so only control events can fill it.
+const EVENT_QUEUE_CAPACITY: usize = 64;
 
-        std::thread::spawn(move || {
-            if let Err(error) = run_wayland(tx.clone()) {
-                let _ = tx.unbounded_send(WaylandEvent::Error {
-                    message: error.to_string(),
-                });
-            }
-        });
+fn wayland_stream() -> event_queue::Receiver<WaylandEvent> {
+    let (sender, receiver) = event_queue::bounded(EVENT_QUEUE_CAPACITY);
 
-        while let Some(event) = rx.next().await {
-            let _ = output.send(event).await;
+    std::thread::spawn(move || {
+        if let Err(error) = run_wayland(sender.clone()) {
+            sender.send(WaylandEvent::Error {
+                message: error.to_string(),
+            });
         }
-    })
+    });
+
+    receiver
 }
+
+/// How long a frame may go without `ready` or `failed` before it is abandoned.
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Attempts per window, including the first one.
+const MAX_CAPTURE_ATTEMPTS: u32 = 3;
+/// Delay before the first retry; doubled for each later one.
+const RETRY_BACKOFF: Duration = Duration::from_millis(250);
+/// Recaptures allowed after blank frames, on top of the first capture.
+const MAX_BLANK_RECAPTURES: u32 = 3;
+/// Delay before recapturing a blank frame; scaled by the recapture number.
+const BLANK_RECAPTURE_DELAY: Duration = Duration::from_millis(400);
+/// Delay before recapturing an activated window, for workspace switch animations to finish.
+const ACTIVATE_RECAPTURE_DELAY: Duration = Duration::from_millis(500);
+/// How long the shm pool outlives the last frame in flight, so back-to-back bursts of
+/// captures (scrolling, resizing) share one pool.
+const SHM_POOL_IDLE: Duration = Duration::from_secs(2);
+
 fn run_wayland(
-    sender: mpsc::UnboundedSender<WaylandEvent>,
+    sender: event_queue::Sender<WaylandEvent>,
 ) -> std::result::Result<(), Box<dyn std::error::Error>> {
     let conn = Connection::connect_to_env()?;
     let (globals, mut queue) = registry_queue_init::<WaylandState>(&conn)?;
@@ -106,108 +277,428 @@ fn run_wayland(
         (),
     )?;
 
-    let mut state = WaylandState::new(sender, shm, toplevel_manager, export_manager);
+    let mut event_loop = EventLoop::<WaylandState>::try_new()?;
+    let (commands, command_source) = channel::channel();
+    event_loop
+        .handle()
+        .insert_source(command_source, |event, _, state| {
+            if let channel::Event::Msg(command) = event {
+                state.handle_command(command);
+            }
+        })
+        .map_err(|error| error.error)?;
+    sender.send(WaylandEvent::Connected(WaylandHandle(commands)));
+    let (processed, processed_source) = channel::channel();
+    event_loop
+        .handle()
+        .insert_source(processed_source, |event, _, state| {
+            if let channel::Event::Msg(frame) = event {
+                state.handle_processed(frame);
+            }
+        })
+        .map_err(|error| error.error)?;
 
-    loop {
-        queue.blocking_dispatch(&mut state)?;
-        conn.flush()?;
-    }
+    let mut state = WaylandState::new(
+        sender,
+        qh,
+        event_loop.handle(),
+        processed,
+        shm,
+        toplevel_manager,
+        export_manager,
+    );
+    // Only needed to activate windows; everything else works without a seat.
+    state.seat = globals.bind::<wl_seat::WlSeat, _, _>(&state.qh, 1..=1, ()).ok();
+
+    // The manager announces every existing toplevel, with its title and app_id, right after
+    // binding, so one roundtrip is enough to know the initial set.
+    queue.roundtrip(&mut state)?;
+    state.sender.send(WaylandEvent::ToplevelsEnumerated);
+
+    WaylandSource::new(conn, queue)
+        .insert(event_loop.handle())
+        .map_err(|error| error.error)?;
+    // Every dispatch can free a slot, change a title, or move the viewport, so the queue is
+    // re-evaluated once per loop iteration rather than at each of those sites.
+    event_loop.run(None, &mut state, |state| {
+        state.pump_captures();
+        state.release_idle_slot_pool();
+    })?;
+    Ok(())
 }
 
 struct WaylandState {
-    sender: mpsc::UnboundedSender<WaylandEvent>,
+    sender: event_queue::Sender<WaylandEvent>,
+    qh: QueueHandle<WaylandState>,
+    loop_handle: LoopHandle<'static, WaylandState>,
+    /// Where workers send finished frames, back onto the event loop.
+    processed: channel::Sender<ProcessedFrame>,
     shm: wl_shm::WlShm,
-    toplevel_manager: zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1,
+    /// Only held so the compositor keeps announcing toplevels.
+    _toplevel_manager: zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1,
     export_manager: hyprland_toplevel_export_manager_v1::HyprlandToplevelExportManagerV1,
+    seat: Option<wl_seat::WlSeat>,
     toplevels: HashMap<u32, ToplevelEntry>,
     pending_frames: HashMap<u32, PendingFrame>,
+    shm_formats: HashSet<wl_shm::Format>,
     slot_pool: Option<SlotPool>,
-    slot_pool_size: usize,
+    /// Timer that unmaps the pool once it has sat idle for [`SHM_POOL_IDLE`].
+    pool_release: Option<RegistrationToken>,
     announce_counter: u64,
+    scheduler: CaptureScheduler,
+    /// Set when titles, visibility or activation changed since the queue was last ranked.
+    priorities_stale: bool,
+    /// Windows the UI currently shows, as last reported by [`WaylandCommand::SetVisible`].
+    visible: HashSet<WindowKey>,
+    /// Toplevel activated through [`WaylandCommand::Activate`]. The picker takes the focus
+    /// back once the compositor reports it active.
+    refocus_after: Option<u32>,
+    thumbnail_size: Option<ThumbnailSizes>,
+    overlay_cursor: bool,
 }
 
 impl WaylandState {
     fn new(
-        sender: mpsc::UnboundedSender<WaylandEvent>,
+        sender: event_queue::Sender<WaylandEvent>,
+        qh: QueueHandle<WaylandState>,
+        loop_handle: LoopHandle<'static, WaylandState>,
+        processed: channel::Sender<ProcessedFrame>,
         shm: wl_shm::WlShm,
         toplevel_manager: zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1,
         export_manager: hyprland_toplevel_export_manager_v1::HyprlandToplevelExportManagerV1,
     ) -> Self {
         Self {
             sender,
+            qh,
+            loop_handle,
+            processed,
             shm,
-            toplevel_manager,
+            _toplevel_manager: toplevel_manager,
             export_manager,
+            seat: None,
             toplevels: HashMap::new(),
             pending_frames: HashMap::new(),
+            // wl_shm guarantees these two even before the format events arrive.
+            shm_formats: HashSet::from([wl_shm::Format::Argb8888, wl_shm::Format::Xrgb8888]),
             slot_pool: None,
-            slot_pool_size: 0,
+            pool_release: None,
             announce_counter: 0,
+            scheduler: CaptureScheduler::new(config::get().max_concurrent_captures),
+            priorities_stale: false,
+            visible: HashSet::new(),
+            refocus_after: None,
+            thumbnail_size: None,
+            overlay_cursor: false,
         }
     }
 
-    fn ensure_slot_pool(&mut self, size: usize) -> &mut SlotPool {
+    /// Allocates a frame buffer, creating the shm pool on first use. The pool grows on its
+    /// own when several frames are in flight.
+    fn create_buffer(&mut self, layout: &FrameLayout) -> Result<Buffer, CaptureError> {
         if self.slot_pool.is_none() {
-            let pool = SlotPool::new(size, self).expect("failed to create shm pool");
+            let pool = SlotPool::new(layout.buffer_len(), self)
+                .map_err(|error| CaptureError::Shm(error.to_string()))?;
             self.slot_pool = Some(pool);
-            self.slot_pool_size = size;
         }
-        if self.slot_pool_size < size {
-            self.slot_pool
-                .as_mut()
-                .expect("slot pool missing")
-                .resize(size)
-                .expect("failed to resize shm pool");
-            self.slot_pool_size = size;
+        let pool = self
+            .slot_pool
+            .as_mut()
+            .ok_or(CaptureError::Protocol("shm pool missing"))?;
+        // FrameLayout caps every dimension well below i32::MAX.
+        let buffer = pool
+            .create_buffer(
+                layout.width as i32,
+                layout.height as i32,
+                layout.stride as i32,
+                layout.format,
+            )
+            .map(|(buffer, _)| buffer)
+            .map_err(|error| CaptureError::Shm(error.to_string()));
+        memory::stats().set_shm_pool(pool.len());
+        buffer
+    }
+
+    /// Unmaps the shm pool once no frame has been in flight for [`SHM_POOL_IDLE`]. The pool
+    /// only ever grows, so keeping it for good would pin the largest burst of captures for the
+    /// picker's whole lifetime; the next capture creates a fresh one at the size it needs.
+    fn release_idle_slot_pool(&mut self) {
+        if !self.pending_frames.is_empty() {
+            if let Some(token) = self.pool_release.take() {
+                self.loop_handle.remove(token);
+            }
+            return;
+        }
+        if self.slot_pool.is_none() || self.pool_release.is_some() {
+            return;
         }
-        self.slot_pool.as_mut().expect("slot pool missing")
+        self.pool_release = self
+            .loop_handle
+            .insert_source(Timer::from_duration(SHM_POOL_IDLE), |_, _, state| {
+                state.pool_release = None;
+                if state.pending_frames.is_empty() && state.slot_pool.take().is_some() {
+                    memory::stats().set_shm_pool(0);
+                }
+                TimeoutAction::Drop
+            })
+            .ok();
     }
 
-    fn request_thumbnail(
-        &mut self,
-        qh: &QueueHandle<Self>,
-        handle: &zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
-    ) {
+    fn handle_command(&mut self, command: WaylandCommand) {
+        match command {
+            WaylandCommand::SetVisible(keys) => {
+                self.visible = keys.into_iter().collect();
+                self.priorities_stale = true;
+            }
+            WaylandCommand::SetThumbnailSize(sizes) => {
+                let previous = self.thumbnail_size.replace(sizes);
+                // The picker moved to an output with another scale: existing thumbnails are
+                // now blurry or oversized, so regenerate them, visible tiles first.
+                if previous.is_some_and(|previous| previous != sizes) {
+                    debug_log(&format!(
+                        "hyprland-export: thumbnail size changed to {}x{}, recapturing",
+                        sizes.grid.width, sizes.grid.height
+                    ));
+                    let ids: Vec<u32> = self.toplevels.keys().copied().collect();
+                    for id in ids {
+                        self.recapture(id);
+                    }
+                }
+            }
+            WaylandCommand::SetOverlayCursor(overlay_cursor) => {
+                if self.overlay_cursor != overlay_cursor {
+                    self.overlay_cursor = overlay_cursor;
+                    let ids: Vec<u32> = self.toplevels.keys().copied().collect();
+                    for id in ids {
+                        self.recapture(id);
+                    }
+                }
+            }
+            WaylandCommand::Recapture(keys) => {
+                let ids: Vec<u32> = self
+                    .toplevels
+                    .keys()
+                    .copied()
+                    .filter(|id| self.window_key(*id).is_some_and(|key| keys.contains(&key)))
+                    .collect();
+                for id in ids {
+                    self.recapture(id);
+                }
+            }
+            WaylandCommand::Activate(key) => {
+                let Some(seat) = &self.seat else {
+                    debug_log("foreign-toplevel: no seat to activate windows on");
+                    return;
+                };
+                let Some(id) = self
+                    .toplevels
+                    .keys()
+                    .copied()
+                    .find(|id| self.window_key(*id).as_ref() == Some(&key))
+                else {
+                    return;
+                };
+                self.toplevels[&id].handle.activate(seat);
+                self.refocus_after = Some(id);
+                let _ = self.loop_handle.insert_source(
+                    Timer::from_duration(ACTIVATE_RECAPTURE_DELAY),
+                    move |_, _, state| {
+                        // A window that was already active never reports it again.
+                        if state.refocus_after == Some(id) {
+                            state.refocus_after = None;
+                        }
+                        if state.toplevels.contains_key(&id) {
+                            state.recapture(id);
+                        }
+                        TimeoutAction::Drop
+                    },
+                );
+            }
+        }
+    }
+
+    /// Queues capture attempt number `attempt` (zero-based) for a toplevel. It starts once
+    /// the window is worth capturing and a slot is free.
+    fn request_thumbnail(&mut self, toplevel_id: u32, attempt: u32) {
+        let priority = self.capture_priority(toplevel_id);
+        if priority == Priority::Skip {
+            if let Some(entry) = self.toplevels.get_mut(&toplevel_id) {
+                entry.capture_skipped = true;
+            }
+            return;
+        }
+        let request = CaptureRequest {
+            toplevel_id,
+            attempt,
+        };
+        self.scheduler.enqueue(request, priority);
+    }
+
+    /// Queues a fresh capture, with a fresh allowance of blank-frame retries.
+    fn recapture(&mut self, toplevel_id: u32) {
+        if let Some(entry) = self.toplevels.get_mut(&toplevel_id) {
+            entry.blank_recaptures = 0;
+        }
+        self.request_thumbnail(toplevel_id, 0);
+    }
+
+    fn capture_priority(&self, toplevel_id: u32) -> Priority {
+        let (Some(entry), Some(key)) = (self.toplevels.get(&toplevel_id), self.window_key(toplevel_id))
+        else {
+            return Priority::Skip;
+        };
+        if !portal::is_listed(&key) || config::get().hides_preview(&key.app_id, &key.title) {
+            Priority::Skip
+        } else if entry.activated {
+            Priority::Focused
+        } else if self.visible.contains(&key) {
+            Priority::Visible
+        } else {
+            Priority::Deferred
+        }
+    }
+
+    /// Starts queued captures until the concurrency limit is reached.
+    fn pump_captures(&mut self) {
+        if self.thumbnail_size.is_none() {
+            return;
+        }
+        if std::mem::take(&mut self.priorities_stale) {
+            let priorities: Vec<Priority> = self
+                .scheduler
+                .waiting()
+                .map(|toplevel_id| self.capture_priority(toplevel_id))
+                .collect();
+            for toplevel_id in self.scheduler.reprioritize(&priorities) {
+                if let Some(entry) = self.toplevels.get_mut(&toplevel_id) {
+                    entry.capture_skipped = true;
+                }
+            }
+        }
+        while let Some(request) = self.scheduler.next() {
+            self.start_capture(request.toplevel_id, request.attempt);
+        }
+    }
+
+    fn start_capture(&mut self, toplevel_id: u32, attempt: u32) {
+        let Some(entry) = self.toplevels.get(&toplevel_id) else {
+            self.scheduler.finished(toplevel_id);
+            return;
+        };
         let frame = self.export_manager.capture_toplevel_with_wlr_toplevel_handle(
-            0,
-            handle,
-            qh,
+            i32::from(self.overlay_cursor),
+            &entry.handle,
+            &self.qh,
             (),
         );
 
         let id = frame.id().protocol_id();
-        let toplevel_id = handle.id().protocol_id();
+        let timeout = self
+            .loop_handle
+            .insert_source(Timer::from_duration(CAPTURE_TIMEOUT), move |_, _, state| {
+                state.expire_frame(id);
+                TimeoutAction::Drop
+            })
+            .ok();
         self.pending_frames.insert(
             id,
             PendingFrame {
+                proxy: frame,
                 toplevel_id,
+                attempt,
+                timeout,
                 width: 0,
                 height: 0,
                 stride: 0,
                 format: None,
+                rejected_formats: Vec::new(),
+                dmabuf_offered: false,
                 y_invert: false,
                 buffer: None,
             },
         );
     }
 
+    /// Gives up on a frame that never answered and retries with backoff while attempts remain.
+    fn expire_frame(&mut self, frame_id: u32) {
+        let Some(frame) = self.finish_frame(frame_id) else { return };
+        self.scheduler.finished(frame.toplevel_id);
+        let attempts = frame.attempt + 1;
+        debug_log(&format!(
+            "hyprland-export: capture timed out for toplevel {} (attempt {attempts})",
+            frame.toplevel_id
+        ));
+        if attempts >= MAX_CAPTURE_ATTEMPTS {
+            self.send_capture_failed(frame.toplevel_id, CaptureError::TimedOut { attempts });
+            return;
+        }
+
+        let toplevel_id = frame.toplevel_id;
+        let backoff = RETRY_BACKOFF * 2u32.pow(frame.attempt);
+        let _ = self
+            .loop_handle
+            .insert_source(Timer::from_duration(backoff), move |_, _, state| {
+                state.request_thumbnail(toplevel_id, attempts);
+                TimeoutAction::Drop
+            });
+    }
+
+    fn handle_processed(&mut self, frame: ProcessedFrame) {
+        self.scheduler.finished(frame.toplevel_id);
+        match frame.result {
+            Ok(FrameOutcome::Thumbnail { size, rgba, zoom }) => {
+                self.send_thumbnail(frame.toplevel_id, size, rgba, zoom);
+            }
+            Ok(FrameOutcome::Blank) => self.handle_blank_frame(frame.toplevel_id),
+            Err(error) => {
+                debug_log(&format!("hyprland-export: {error}"));
+                self.send_capture_failed(frame.toplevel_id, CaptureError::InvalidFrame(error));
+            }
+        }
+    }
+
+    /// Schedules another capture after a blank frame. Once the recaptures run out, frames are
+    /// kept however they look; see [`FrameJob::keep_blank`].
+    fn handle_blank_frame(&mut self, toplevel_id: u32) {
+        let Some(entry) = self.toplevels.get_mut(&toplevel_id) else { return };
+        entry.blank_recaptures += 1;
+        let delay = BLANK_RECAPTURE_DELAY * entry.blank_recaptures;
+        debug_log(&format!(
+            "hyprland-export: blank frame for toplevel {toplevel_id}; recapturing in {delay:?}"
+        ));
+        let _ = self
+            .loop_handle
+            .insert_source(Timer::from_duration(delay), move |_, _, state| {
+                state.request_thumbnail(toplevel_id, 0);
+                TimeoutAction::Drop
+            });
+    }
+
+    /// Removes a frame from the pending set, destroying its proxy and cancelling its deadline.
+    /// The toplevel keeps its scheduler slot until the caller is done with the frame.
+    fn finish_frame(&mut self, frame_id: u32) -> Option<PendingFrame> {
+        let frame = self.pending_frames.remove(&frame_id)?;
+        frame.proxy.destroy();
+        if let Some(timeout) = frame.timeout {
+            self.loop_handle.remove(timeout);
+        }
+        Some(frame)
+    }
+
     fn send_upsert(&self, id: u32, title: &str, app_id: &str) {
-        let _ = self.sender.unbounded_send(WaylandEvent::Upsert {
+        self.sender.send(WaylandEvent::Upsert {
             id,
             title: title.to_string(),
             app_id: app_id.to_string(),
         });
     }
 
-    fn send_thumbnail(&self, id: u32, width: u32, height: u32, rgba: Vec<u8>) {
-        let Some(entry) = self.toplevels.get(&id) else { return };
-        let key_app = entry.app_id.clone();
-        let key_title = entry.title.clone();
+    fn window_key(&self, id: u32) -> Option<WindowKey> {
+        let entry = self.toplevels.get(&id)?;
 
         let mut matching: Vec<&ToplevelEntry> = self
             .toplevels
             .values()
-            .filter(|e| e.app_id == key_app && e.title == key_title)
+            .filter(|e| e.app_id == entry.app_id && e.title == entry.title)
             .collect();
         matching.sort_by_key(|e| e.announce_order);
 
@@ -217,19 +708,67 @@ impl WaylandState {
             .position(|e| e.announce_order == entry.announce_order)
             .unwrap_or(0);
 
-        let _ = self.sender.unbounded_send(WaylandEvent::Thumbnail {
-            title: key_title,
-            app_id: key_app,
+        Some(WindowKey {
+            app_id: entry.app_id.clone(),
+            title: entry.title.clone(),
             group_index,
             group_size,
-            width,
-            height,
-            rgba,
+        })
+    }
+
+    fn send_thumbnail(&self, id: u32, size: Size<u32>, rgba: Bytes, zoom: Option<(Size<u32>, Bytes)>) {
+        let Some(key) = self.window_key(id) else { return };
+        // Pixels of windows hidden by a rule never leave this thread.
+        if config::get().hides_preview(&key.app_id, &key.title) {
+            return;
+        }
+        // A newer frame makes any still-queued one for the same window pointless.
+        let supersedes = |queued: &WaylandEvent| {
+            matches!(queued, WaylandEvent::Thumbnail { key: queued, .. } if *queued == key)
+        };
+        self.sender.send_coalescing(
+            WaylandEvent::Thumbnail {
+                key: key.clone(),
+                width: size.width,
+                height: size.height,
+                rgba,
+                zoom,
+            },
+            supersedes,
+        );
+    }
+
+    /// Re-announces the parent links touching `id`. Keys are title-based, so links are sent
+    /// again whenever either side of the relationship finishes a state update.
+    fn send_parent_links(&self, id: u32) {
+        let related = self.toplevels.iter().filter(|(child_id, entry)| {
+            let is_self = **child_id == id && (entry.parent.is_some() || entry.parent_changed);
+            is_self || entry.parent == Some(id)
         });
+        for (child_id, entry) in related {
+            let Some(child) = self.window_key(*child_id) else { continue };
+            let parent = entry.parent.and_then(|parent_id| self.window_key(parent_id));
+            self.sender.send(WaylandEvent::Parent { child, parent });
+        }
+    }
+
+    /// Drops a frame that can't produce a thumbnail and reports why to the UI.
+    fn fail_frame(&mut self, frame_id: u32, error: CaptureError) {
+        debug_log(&format!("hyprland-export: {error}"));
+        if let Some(frame) = self.finish_frame(frame_id) {
+            self.scheduler.finished(frame.toplevel_id);
+            self.send_capture_failed(frame.toplevel_id, error);
+        }
+    }
+
+    fn send_capture_failed(&self, toplevel_id: u32, error: CaptureError) {
+        if let Some(key) = self.window_key(toplevel_id) {
+            self.sender.send(WaylandEvent::CaptureFailed { key, error });
+        }
     }
 
     fn send_remove(&self, id: u32) {
-        let _ = self.sender.unbounded_send(WaylandEvent::Remove { id });
+        self.sender.send(WaylandEvent::Remove { id });
     }
 }
 
@@ -237,16 +776,106 @@ struct ToplevelEntry {
     handle: zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
     title: String,
     app_id: String,
-    captured: bool,
+    /// Holds keyboard focus in the compositor.
+    activated: bool,
+    /// Gained focus since the last `done`, which is when it is reported.
+    newly_activated: bool,
+    blank_recaptures: u32,
     announce_order: u64,
+    parent: Option<u32>,
+    parent_changed: bool,
+    /// Title or app_id changed since the last `done`.
+    key_changed: bool,
+    /// A capture was dropped because the window isn't listed or is hidden by a rule. A later
+    /// title can still get it listed, which queues the capture again.
+    capture_skipped: bool,
+}
+
+/// A copied frame on its way to the worker pool.
+struct FrameJob {
+    toplevel_id: u32,
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: wl_shm::Format,
+    y_invert: bool,
+    style: ThumbnailStyle,
+    /// Use the frame even if it looks blank. Set for the last recapture: some windows really
+    /// are black, like a terminal with a black background, and their frames are valid.
+    keep_blank: bool,
+}
+
+impl FrameJob {
+    /// Converts and downscales the frame. Runs on a rayon worker, never on the event loop.
+    fn process(self, sizes: ThumbnailSizes) -> ProcessedFrame {
+        let result = convert::convert_to_rgba(
+            &self.data,
+            self.width,
+            self.height,
+            self.stride,
+            self.format,
+            self.y_invert,
+        )
+        .map(|rgba| {
+            if !self.keep_blank && convert::is_blank(&rgba) {
+                FrameOutcome::Blank
+            } else {
+                // The zoom pane always shows the whole window. Skip the copy when it would
+                // be no sharper than the grid thumbnail.
+                let zoom = sizes.zoom.filter(|&zoom| {
+                    let fitted = resample::fit_within(self.width, self.height, zoom);
+                    fitted.width > sizes.grid.width || fitted.height > sizes.grid.height
+                });
+                let ((size, rgba), zoom) = resample::thumbnail(
+                    rgba,
+                    self.width,
+                    self.height,
+                    sizes.grid,
+                    zoom,
+                    self.style,
+                );
+                FrameOutcome::Thumbnail {
+                    size,
+                    rgba: Bytes::from(rgba),
+                    zoom: zoom.map(|(size, rgba)| (size, Bytes::from(rgba))),
+                }
+            }
+        });
+        ProcessedFrame {
+            toplevel_id: self.toplevel_id,
+            result,
+        }
+    }
+}
+
+/// A worker's answer for one frame, or why the frame was unusable.
+struct ProcessedFrame {
+    toplevel_id: u32,
+    result: Result<FrameOutcome, FrameError>,
+}
+
+enum FrameOutcome {
+    Thumbnail {
+        size: Size<u32>,
+        rgba: Bytes,
+        zoom: Option<(Size<u32>, Bytes)>,
+    },
+    /// All black or fully transparent; see [`convert::is_blank`].
+    Blank,
 }
 
 struct PendingFrame {
+    proxy: hyprland_toplevel_export_frame_v1::HyprlandToplevelExportFrameV1,
     toplevel_id: u32,
+    attempt: u32,
+    timeout: Option<RegistrationToken>,
     width: u32,
     height: u32,
     stride: u32,
     format: Option<wl_shm::Format>,
+    rejected_formats: Vec<WEnum<wl_shm::Format>>,
+    dmabuf_offered: bool,
     y_invert: bool,
     buffer: Option<Buffer>,
 }
@@ -271,9 +900,27 @@ impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for WaylandState {
 
 impl Dispatch<wl_shm::WlShm, ()> for WaylandState {
     fn event(
-        _state: &mut Self,
+        state: &mut Self,
         _proxy: &wl_shm::WlShm,
-        _event: wl_shm::Event,
+        event: wl_shm::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_shm::Event::Format {
+            format: WEnum::Value(format),
+        } = event
+        {
+            state.shm_formats.insert(format);
+        }
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &wl_seat::WlSeat,
+        _event: wl_seat::Event,
         _data: &(),
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
@@ -288,7 +935,7 @@ impl Dispatch<zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1, ()
         event: zwlr_foreign_toplevel_manager_v1::Event,
         _data: &(),
         _conn: &Connection,
-        qh: &QueueHandle<Self>,
+        _qh: &QueueHandle<Self>,
     ) {
         match event {
             zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } => {
@@ -297,18 +944,19 @@ impl Dispatch<zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1, ()
                     handle: toplevel.clone(),
                     title: String::new(),
                     app_id: String::new(),
-                    captured: false,
+                    activated: false,
+                    newly_activated: false,
+                    blank_recaptures: 0,
                     announce_order: state.announce_counter,
+                    parent: None,
+                    parent_changed: false,
+                    key_changed: false,
+                    capture_skipped: false,
                 };
                 state.announce_counter += 1;
                 state.toplevels.insert(id, entry);
                 state.send_upsert(id, "", "");
-                if let Some(entry) = state.toplevels.get_mut(&id) {
-                    if !entry.captured {
-                        entry.captured = true;
-                        state.request_thumbnail(qh, &toplevel);
-                    }
-                }
+                state.request_thumbnail(id, 0);
             }
             zwlr_foreign_toplevel_manager_v1::Event::Finished => {}
             _ => {}
@@ -339,18 +987,87 @@ impl Dispatch<zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1, ()>
             match event {
                 zwlr_foreign_toplevel_handle_v1::Event::Title { title } => {
                     entry.title = title;
+                    entry.key_changed = true;
                     let title = entry.title.clone();
                     let app_id = entry.app_id.clone();
                     state.send_upsert(id, &title, &app_id);
                 }
                 zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
                     entry.app_id = app_id;
+                    entry.key_changed = true;
                     let title = entry.title.clone();
                     let app_id = entry.app_id.clone();
                     state.send_upsert(id, &title, &app_id);
                 }
+                zwlr_foreign_toplevel_handle_v1::Event::Parent { parent } => {
+                    entry.parent = parent.map(|parent| parent.id().protocol_id());
+                    entry.parent_changed = true;
+                }
+                zwlr_foreign_toplevel_handle_v1::Event::State { state: states } => {
+                    let activated = zwlr_foreign_toplevel_handle_v1::State::Activated as u32;
+                    let was_activated = entry.activated;
+                    entry.activated = states
+                        .chunks_exact(4)
+                        .any(|chunk| chunk == activated.to_ne_bytes());
+                    entry.newly_activated |= entry.activated && !was_activated;
+                    state.priorities_stale |= entry.activated != was_activated;
+                }
+                zwlr_foreign_toplevel_handle_v1::Event::Done => {
+                    state.send_parent_links(id);
+                    let mut newly_activated = false;
+                    let mut retry_skipped = false;
+                    if let Some(entry) = state.toplevels.get_mut(&id) {
+                        entry.parent_changed = false;
+                        newly_activated = std::mem::take(&mut entry.newly_activated);
+                        if std::mem::take(&mut entry.key_changed) {
+                            // Keys of same-titled windows shift too, so rank everything again.
+                            state.priorities_stale = true;
+                            retry_skipped = std::mem::take(&mut entry.capture_skipped);
+                        }
+                    }
+                    if retry_skipped {
+                        state.request_thumbnail(id, 0);
+                    }
+                    if newly_activated && state.refocus_after == Some(id) {
+                        state.refocus_after = None;
+                        // Hyprland puts the activated window in front of the picker, which is
+                        // meant to stay open in front. IPC would stall captures, so it runs on
+                        // a thread of its own.
+                        std::thread::spawn(|| {
+                            if let Err(error) = hyprland::focus_own_window() {
+                                debug_log(&format!("hyprland: can't refocus the picker: {error}"));
+                            }
+                        });
+                    }
+                    if newly_activated && let Some(key) = state.window_key(id) {
+                        state.sender.send(WaylandEvent::Activated { key });
+                    }
+                }
                 zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                    let stale: Vec<u32> = state
+                        .pending_frames
+                        .iter()
+                        .filter(|(_, frame)| frame.toplevel_id == id)
+                        .map(|(frame_id, _)| *frame_id)
+                        .collect();
+                    for frame_id in stale {
+                        state.finish_frame(frame_id);
+                    }
                     state.toplevels.remove(&id);
+                    state.scheduler.forget(id);
+                    let orphans: Vec<u32> = state
+                        .toplevels
+                        .iter()
+                        .filter(|(_, entry)| entry.parent == Some(id))
+                        .map(|(orphan, _)| *orphan)
+                        .collect();
+                    for orphan in orphans {
+                        if let Some(entry) = state.toplevels.get_mut(&orphan) {
+                            entry.parent = None;
+                            entry.parent_changed = true;
+                        }
+                        state.send_parent_links(orphan);
+                    }
                     state.send_remove(id);
                 }
                 _ => {}
@@ -386,148 +1103,140 @@ impl Dispatch<hyprland_toplevel_export_frame_v1::HyprlandToplevelExportFrameV1,
         }
 
         match event {
-                hyprland_toplevel_export_frame_v1::Event::Buffer {
-                    format,
-                    width,
-                    height,
-                    stride,
-                } => {
-                    if let Some(frame) = state.pending_frames.get_mut(&id) {
-                        if let WEnum::Unknown(value) = format {
-                            debug_log(&format!(
-                                "hyprland-export: unknown shm format {} (expected ARGB/XRGB)",
-                                value
-                            ));
-                        }
-                        frame.format = match format {
-                            WEnum::Value(value) => Some(value),
-                            _ => None,
-                        };
-                        frame.width = width;
+            hyprland_toplevel_export_frame_v1::Event::Buffer {
+                format,
+                width,
+                height,
+                stride,
+            } => {
+                let usable = match format {
+                    WEnum::Value(format) => {
+                        convert::is_supported(format) && state.shm_formats.contains(&format)
+                    }
+                    WEnum::Unknown(_) => false,
+                };
+                let Some(frame) = state.pending_frames.get_mut(&id) else { return };
+                if !usable {
+                    debug_log(&format!(
+                        "hyprland-export: ignoring shm format {format:?} (not convertible or not advertised by wl_shm)"
+                    ));
+                    frame.rejected_formats.push(format);
+                    return;
+                }
+                if let WEnum::Value(format) = format
+                    && frame.format.is_none()
+                {
+                    frame.format = Some(format);
+                    frame.width = width;
                     frame.height = height;
                     frame.stride = stride;
                 }
             }
-            hyprland_toplevel_export_frame_v1::Event::Flags { flags } => {
+            hyprland_toplevel_export_frame_v1::Event::LinuxDmabuf { .. } => {
                 if let Some(frame) = state.pending_frames.get_mut(&id) {
-                    if let WEnum::Value(value) = flags {
-                        frame.y_invert =
-                            value.contains(hyprland_toplevel_export_frame_v1::Flags::YInvert);
-                    }
+                    frame.dmabuf_offered = true;
                 }
             }
-                hyprland_toplevel_export_frame_v1::Event::BufferDone => {
-                    let (width, height, stride, format, has_buffer) = {
-                        let frame = state.pending_frames.get(&id).expect("frame missing");
-                        (
-                            frame.width,
-                        frame.height,
-                        frame.stride,
-                        frame.format,
-                        frame.buffer.is_some(),
-                    )
-                };
-                if has_buffer {
+            hyprland_toplevel_export_frame_v1::Event::Flags { flags } => {
+                if let Some(frame) = state.pending_frames.get_mut(&id)
+                    && let WEnum::Value(value) = flags
+                {
+                    frame.y_invert =
+                        value.contains(hyprland_toplevel_export_frame_v1::Flags::YInvert);
+                }
+            }
+            hyprland_toplevel_export_frame_v1::Event::BufferDone => {
+                let Some(frame) = state.pending_frames.get(&id) else { return };
+                if frame.buffer.is_some() {
                     return;
                 }
-                    let format = match format {
-                        Some(wl_shm::Format::Argb8888) => wl_shm::Format::Argb8888,
-                        Some(wl_shm::Format::Xrgb8888) => wl_shm::Format::Xrgb8888,
-                        _ => {
-                            debug_log("hyprland-export: unsupported shm format; skipping thumbnail");
-                            proxy.destroy();
-                            return;
-                        }
+                let Some(format) = frame.format else {
+                    let error = if frame.dmabuf_offered && frame.rejected_formats.is_empty() {
+                        CaptureError::DmabufOnly
+                    } else {
+                        CaptureError::UnsupportedFormat(frame.rejected_formats.clone())
                     };
+                    state.fail_frame(id, error);
+                    return;
+                };
+                let frame_attempt = frame.attempt;
+                let layout = match FrameLayout::new(frame.width, frame.height, frame.stride, format) {
+                    Ok(layout) => layout,
+                    Err(error) => {
+                        state.fail_frame(id, CaptureError::InvalidFrame(error));
+                        return;
+                    }
+                };
 
-                let size = (stride * height) as usize;
-                let pool = state.ensure_slot_pool(size);
-                let (buffer, _) = pool
-                    .create_buffer(width as i32, height as i32, stride as i32, format)
-                    .expect("failed to create shm buffer");
+                let buffer = match state.create_buffer(&layout) {
+                    Ok(buffer) => buffer,
+                    Err(error) => {
+                        state.fail_frame(id, error);
+                        return;
+                    }
+                };
 
-                proxy.copy(buffer.wl_buffer(), 0);
+                // Retries ignore damage: a window that never repaints would otherwise never answer.
+                let ignore_damage = i32::from(frame_attempt > 0);
+                proxy.copy(buffer.wl_buffer(), ignore_damage);
                 if let Some(frame) = state.pending_frames.get_mut(&id) {
                     frame.buffer = Some(buffer);
                 }
             }
             hyprland_toplevel_export_frame_v1::Event::Ready { .. } => {
-                let (buffer, width, height, stride, format, y_invert, toplevel_id) = {
-                    let frame = state.pending_frames.get_mut(&id).expect("frame missing");
-                    (
-                        frame.buffer.take(),
-                        frame.width,
-                        frame.height,
-                        frame.stride,
-                        frame.format.unwrap_or(wl_shm::Format::Argb8888),
-                        frame.y_invert,
-                        frame.toplevel_id,
-                    )
+                let Some(frame) = state.pending_frames.get_mut(&id) else { return };
+                let (buffer, width, height, stride, format, y_invert, toplevel_id) = (
+                    frame.buffer.take(),
+                    frame.width,
+                    frame.height,
+                    frame.stride,
+                    frame.format,
+                    frame.y_invert,
+                    frame.toplevel_id,
+                );
+                let (Some(buffer), Some(format)) = (buffer, format) else {
+                    state.fail_frame(id, CaptureError::Protocol("ready before a buffer was attached"));
+                    return;
                 };
-                if let (Some(buffer), Some(pool)) = (buffer, state.slot_pool.as_mut()) {
-                    if let Some(data) = buffer.canvas(pool) {
-                        let rgba = convert_to_rgba(data, width, height, stride, format, y_invert);
-                        state.send_thumbnail(toplevel_id, width, height, rgba);
-                    }
-                }
-                proxy.destroy();
-                state.pending_frames.remove(&id);
+                let Some(data) = state.slot_pool.as_mut().and_then(|pool| buffer.canvas(pool)) else {
+                    state.fail_frame(id, CaptureError::Shm("frame buffer is no longer mapped".into()));
+                    return;
+                };
+                let style = state
+                    .toplevels
+                    .get(&toplevel_id)
+                    .map(|entry| config::get().style_for(&entry.app_id, &entry.title))
+                    .unwrap_or_else(|| config::get().style_for("", ""));
+                let keep_blank = state
+                    .toplevels
+                    .get(&toplevel_id)
+                    .is_some_and(|entry| entry.blank_recaptures >= MAX_BLANK_RECAPTURES);
+                // One memcpy frees the shm slot right away; everything heavier runs on the pool.
+                let job = FrameJob {
+                    toplevel_id,
+                    data: data.to_vec(),
+                    width,
+                    height,
+                    stride,
+                    format,
+                    y_invert,
+                    style,
+                    keep_blank,
+                };
+                state.finish_frame(id);
+                let Some(sizes) = state.thumbnail_size else {
+                    state.scheduler.finished(toplevel_id);
+                    return;
+                };
+                let results = state.processed.clone();
+                rayon::spawn(move || {
+                    let _ = results.send(job.process(sizes));
+                });
             }
             hyprland_toplevel_export_frame_v1::Event::Failed => {
-                debug_log("hyprland-export: capture failed for a toplevel");
-                proxy.destroy();
-                state.pending_frames.remove(&id);
+                state.fail_frame(id, CaptureError::Failed);
             }
             _ => {}
         }
     }
 }
-
-fn convert_to_rgba(
-    data: &[u8],
-    width: u32,
-    height: u32,
-    stride: u32,
-    format: wl_shm::Format,
-    y_invert: bool,
-) -> Vec<u8> {
-    let mut out = vec![0u8; (width * height * 4) as usize];
-    let row_bytes = (width * 4) as usize;
-    for y in 0..height {
-        let src_y = if y_invert { height - 1 - y } else { y };
-        let src_start = (src_y * stride) as usize;
-        let src_row = &data[src_start..src_start + row_bytes];
-        let dst_start = (y * width * 4) as usize;
-        let dst_row = &mut out[dst_start..dst_start + row_bytes];
-
-        for x in 0..width {
-            let i = (x * 4) as usize;
-            let px = u32::from_ne_bytes([
-                src_row[i],
-                src_row[i + 1],
-                src_row[i + 2],
-                src_row[i + 3],
-            ]);
-            let (a, r, g, b) = match format {
-                wl_shm::Format::Argb8888 => (
-                    ((px >> 24) & 0xff) as u8,
-                    ((px >> 16) & 0xff) as u8,
-                    ((px >> 8) & 0xff) as u8,
-                    (px & 0xff) as u8,
-                ),
-                wl_shm::Format::Xrgb8888 => (
-                    0xff,
-                    ((px >> 16) & 0xff) as u8,
-                    ((px >> 8) & 0xff) as u8,
-                    (px & 0xff) as u8,
-                ),
-                _ => (0xff, 0, 0, 0),
-            };
-            dst_row[i] = r;
-            dst_row[i + 1] = g;
-            dst_row[i + 2] = b;
-            dst_row[i + 3] = a;
-        }
-    }
-    out
-}
@@ -0,0 +1,121 @@
+use iced::advanced::widget::operation::scrollable::{scroll_to, AbsoluteOffset};
+use iced::advanced::widget::operation::{Operation, Outcome, Scrollable};
+use iced::advanced::widget::{operate, Id};
+use iced::widget::canvas;
+use iced::{mouse, Color, Point, Rectangle, Renderer, Size, Task, Theme, Vector};
+
+const CHECKER_SIZE: f32 = 8.0;
+
+/// Light/dark squares drawn behind translucent thumbnails.
+pub struct Checkerboard;
+
+impl<Message> canvas::Program<Message> for Checkerboard {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<canvas::Geometry> {
+        let palette = theme.extended_palette();
+        let light = palette.background.base.color;
+        let dark = mix(light, palette.background.base.text, 0.15);
+
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        frame.fill_rectangle(Point::ORIGIN, bounds.size(), light);
+
+        let columns = (bounds.width / CHECKER_SIZE).ceil() as usize;
+        let rows = (bounds.height / CHECKER_SIZE).ceil() as usize;
+        for row in 0..rows {
+            for column in (row % 2..columns).step_by(2) {
+                let origin = Point::new(column as f32 * CHECKER_SIZE, row as f32 * CHECKER_SIZE);
+                let size = Size::new(
+                    CHECKER_SIZE.min(bounds.width - origin.x),
+                    CHECKER_SIZE.min(bounds.height - origin.y),
+                );
+                frame.fill_rectangle(origin, size, dark);
+            }
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+/// Linear blend from `a` towards `b`, always opaque.
+pub fn mix(a: Color, b: Color, amount: f32) -> Color {
+    Color {
+        r: a.r + (b.r - a.r) * amount,
+        g: a.g + (b.g - a.g) * amount,
+        b: a.b + (b.b - a.b) * amount,
+        a: 1.0,
+    }
+}
+
+/// Scrolls `scrollable` vertically just far enough to show the container with id `target`.
+/// Does nothing if it is already in view, or either widget isn't on screen.
+pub fn scroll_into_view<T: Send + 'static>(scrollable: Id, target: Id) -> Task<T> {
+    operate(ScrollIntoView {
+        scrollable,
+        target,
+        viewport: None,
+        target_bounds: None,
+    })
+}
+
+struct ScrollIntoView {
+    scrollable: Id,
+    target: Id,
+    /// The scrollable's bounds, its content's bounds, and how far it is scrolled.
+    viewport: Option<(Rectangle, Rectangle, Vector)>,
+    target_bounds: Option<Rectangle>,
+}
+
+impl<T: 'static> Operation<T> for ScrollIntoView {
+    fn traverse(&mut self, operate: &mut dyn FnMut(&mut dyn Operation<T>)) {
+        operate(self);
+    }
+
+    fn container(&mut self, id: Option<&Id>, bounds: Rectangle) {
+        if id == Some(&self.target) {
+            self.target_bounds = Some(bounds);
+        }
+    }
+
+    fn scrollable(
+        &mut self,
+        id: Option<&Id>,
+        bounds: Rectangle,
+        content_bounds: Rectangle,
+        translation: Vector,
+        _state: &mut dyn Scrollable,
+    ) {
+        if id == Some(&self.scrollable) {
+            self.viewport = Some((bounds, content_bounds, translation));
+        }
+    }
+
+    /// The layout is only known once the whole tree was visited, so the scroll itself is a
+    /// second operation.
+    fn finish(&self) -> Outcome<T> {
+        let (Some((bounds, content, translation)), Some(target)) =
+            (self.viewport, self.target_bounds)
+        else {
+            return Outcome::None;
+        };
+        // Layout bounds ignore scrolling, so this is the target's offset into the content.
+        let top = target.y - content.y;
+        let bottom = top + target.height;
+        let y = if top < translation.y {
+            top
+        } else if bottom > translation.y + bounds.height {
+            bottom - bounds.height
+        } else {
+            return Outcome::None;
+        };
+        let offset = AbsoluteOffset { x: None, y: Some(y) };
+        Outcome::Chain(Box::new(scroll_to(self.scrollable.clone(), offset)))
+    }
+}